@@ -68,6 +68,73 @@ impl Camera {
     pub fn get_image_size(&self) -> (usize, usize) {
         (self.width, self.height)
     }
+
+    // Unit direction the camera looks in
+    fn view_direction(&self) -> Vec3 {
+        -self.forward.normalized()
+    }
+
+    // Area on the focus plane the pixels cover; get_ray takes u and v past 1
+    // for the last column and row, see SamplePointGenerator
+    fn film_area(&self) -> Float {
+        let columns = self.width as Float / (self.width - 1).max(1) as Float;
+        let rows = self.height as Float / (self.height - 1).max(1) as Float;
+        self.horizontal.length() * columns * self.vertical.length() * rows
+    }
+
+    // (u, v) as get_ray takes them of the ray from lens_point through point,
+    // with the cosine between the ray and the view direction
+    fn film_coordinates(&self, lens_point: &Vec3, point: &Vec3) -> Option<(Float, Float, Float)> {
+        let direction = (*point - *lens_point).normalized();
+        let cos = direction.dot(&self.view_direction());
+        if cos <= 1e-6 {
+            return None;
+        }
+        // the lens lies in the plane through position, focus distance from the film
+        let film_point = *lens_point + direction * (self.forward.length() / cos);
+        let offset = film_point - self.viewport_upper_left;
+        let u = offset.dot(&self.horizontal) / self.horizontal.squared_length();
+        let v = -offset.dot(&self.vertical) / self.vertical.squared_length();
+        Some((u, v, cos))
+    }
+
+    fn pixel(&self, u: Float, v: Float) -> Option<(usize, usize)> {
+        if u < 0.0 || v < 0.0 {
+            return None;
+        }
+        let x = (u * (self.width - 1).max(1) as Float) as usize;
+        let y = (v * (self.height - 1).max(1) as Float) as usize;
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some((x, y))
+    }
+
+    // Light tracing: a uniform point on the lens, the position itself for a pinhole
+    pub fn sample_lens(&self) -> Vec3 {
+        let p = Vec3::new_random_in_unit_disk();
+        self.position + p[0]*self.defocus_disk_u + p[1]*self.defocus_disk_v
+    }
+
+    // Solid angle density of get_ray sending a ray along direction, over the whole image
+    pub fn pdf_direction(&self, direction: &Vec3) -> Float {
+        let Some((u, v, cos)) = self.film_coordinates(&self.position, &(self.position + *direction)) else {
+            return 0.0;
+        };
+        if self.pixel(u, v).is_none() {
+            return 0.0;
+        }
+        self.forward.squared_length() / (self.film_area() * cos * cos * cos)
+    }
+
+    // Light tracing: the pixel point shows up in through lens_point, with the
+    // camera importance over the solid angle density of lens_point seen from point
+    pub fn importance(&self, lens_point: &Vec3, point: &Vec3) -> Option<((usize, usize), Float)> {
+        let (u, v, cos) = self.film_coordinates(lens_point, point)?;
+        let pixel = self.pixel(u, v)?;
+        let distance_squared = (*point - *lens_point).squared_length();
+        Some((pixel, self.forward.squared_length() / (self.film_area() * cos * cos * cos * distance_squared)))
+    }
 }
 
 #[cfg(test)]
@@ -104,6 +171,38 @@ mod test {
         assert_eq!(camera.vertical, Vec3::new(0.0, 2.0, 0.0));
     }
 
+    #[test]
+    fn test_importance() {
+        let camera = Camera::new(
+            2.0,
+            5.0,
+            Vec3::new(1.0, 0.5, 0.0),
+            Vec3::new(0.0, 0.0, -3.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            32, 24
+        );
+        // a point seen through get_ray lands back in its pixel
+        for (x, y) in [(0, 0), (5, 17), (31, 23)] {
+            let u = (x as Float + 0.5) / 31.0;
+            let v = (y as Float + 0.5) / 23.0;
+            let ray = camera.get_ray(u, v);
+            let point = ray.at(7.0);
+            let (pixel, _) = camera.importance(&ray.origin(), &point).unwrap();
+            assert_eq!(pixel, (x, y));
+        }
+        assert!(camera.importance(&camera.position, &Vec3::new(1.0, 0.5, 5.0)).is_none());
+
+        // the direction density integrates to one over the image
+        let n = 200000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            sum += camera.pdf_direction(&Vec3::new_random_unit_vector());
+        }
+        sum *= 4.0 * crate::FloatConsts::PI / n as Float;
+        assert!((sum - 1.0).abs() < 0.03, "{}", sum);
+    }
+
     #[test]
     #[ignore]
     fn test_ray_image_generation() {
@@ -19,6 +19,10 @@ impl AABB {
         Self { min, max }
     }
 
+    pub fn from_bounds(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
     pub fn min(&self) -> Vec3 {
         self.min
     }
@@ -33,7 +37,11 @@ impl AABB {
         Self { min, max }
     }
 
-    pub fn intersect(&self, ray: &Ray, mut t_range: Range<Float>) -> bool {
+    pub fn intersect(&self, ray: &Ray, t_range: Range<Float>) -> bool {
+        self.intersect_range(ray, t_range).is_some()
+    }
+
+    pub fn intersect_range(&self, ray: &Ray, mut t_range: Range<Float>) -> Option<Range<Float>> {
         let o = ray.origin();
         let d = ray.direction();
         for i in 0..3 {
@@ -54,10 +62,15 @@ impl AABB {
             }
 
             if t_range.end <= t_range.start {
-                return false;
+                return None;
             }
         }
-        true
+        Some(t_range)
+    }
+
+    pub fn surface_area(&self) -> Float {
+        let d = self.max - self.min;
+        Float::from(2.0) * (d.x * d.y + d.y * d.z + d.z * d.x)
     }
 
     pub fn longest_axis(&self) -> usize {
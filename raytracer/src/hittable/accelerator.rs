@@ -0,0 +1,74 @@
+use super::Hittable;
+
+// Anything that can stand in for the whole scene when tracing rays.
+// Samplers share one instance between threads, hence Send + Sync.
+pub trait Accelerator: Hittable + Send + Sync {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum AcceleratorType {
+    #[default]
+    BVH,
+    Grid,
+    KdTree,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{hittable::{bvh::BVH, grid::Grid, kdtree::KdTree, quad::Quad, sphere::Sphere, HitRecord}, material::{lambertian::Lambertian, Material}, math::vec3::Vec3, ray::Ray, Float};
+
+    use super::*;
+
+    fn dummy_objects() -> Vec<Arc<Box<dyn Hittable>>> {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::zero())));
+        let mut objects: Vec<Arc<Box<dyn Hittable>>> = Vec::new();
+        for i in 0..50 {
+            let center = Vec3::new((i % 5) as Float, ((i / 5) % 5) as Float, -((i / 25) as Float) * 2.0);
+            objects.push(Arc::new(Box::new(Sphere::new(center, 0.3 + 0.01 * i as Float, material.clone()))));
+        }
+        objects.push(Arc::new(Box::new(Quad::new(
+            Vec3::new(-10.0, -1.0, -10.0),
+            Vec3::new(20.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 20.0),
+            material.clone(),
+        ))));
+        objects.push(Arc::new(Box::new(Sphere::new(Vec3::new(2.0, 2.0, -1.0), 4.0, material))));
+        objects
+    }
+
+    fn brute_force(objects: &Vec<Arc<Box<dyn Hittable>>>, ray: &Ray) -> Option<HitRecord> {
+        let mut closest = Float::INFINITY;
+        let mut hit_record = None;
+        for object in objects {
+            if let Some(rec) = object.hit(ray, 0.001..closest) {
+                closest = rec.t;
+                hit_record = Some(rec);
+            }
+        }
+        hit_record
+    }
+
+    #[test]
+    fn test_accelerators_match_brute_force() {
+        let objects = dummy_objects();
+        let accelerators: Vec<Box<dyn Accelerator>> = vec![
+            Box::new(BVH::new(&objects)),
+            Box::new(Grid::new(&objects)),
+            Box::new(KdTree::new(&objects)),
+        ];
+        for _ in 0..2000 {
+            let origin = Vec3::new_random_range(-8.0..8.0);
+            let ray = Ray::new(origin, Vec3::new_random_unit_vector());
+            let expected = brute_force(&objects, &ray).map(|rec| rec.t);
+            for accelerator in &accelerators {
+                let actual = accelerator.hit(&ray, 0.001..Float::INFINITY).map(|rec| rec.t);
+                match (expected, actual) {
+                    (Some(e), Some(a)) => assert!((e - a).abs() < 1e-4, "expected t={}, got t={}", e, a),
+                    (None, None) => {},
+                    _ => panic!("expected {:?}, got {:?}", expected, actual),
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,56 @@
-use std::{cmp::Ordering, ops::Range, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, io, ops::Range, path::Path, sync::Arc};
 
 use crate::{hittable::{HitRecord, Hittable}, ray::Ray, utils::random::random_range, Float};
 
-use super::aabb::AABB;
+use super::{accelerator::Accelerator, aabb::AABB, bvhcache::{self, FlatNode, NO_INDEX}, stats};
 
 pub struct BVH {
     root: Arc<Node>,
 }
 
 impl BVH {
-    pub fn new(list: &Vec<Arc<Box<dyn Hittable>>>) -> Self {
-        let mut objects = list.clone();
+    pub fn new(list: &[Arc<Box<dyn Hittable>>]) -> Self {
+        let mut objects = list.to_vec();
         Self::new_with_mut_slice(&mut objects)
     }
 
+    pub fn new_cached(list: &[Arc<Box<dyn Hittable>>], path: &Path) -> Self {
+        if let Ok(Some(bvh)) = Self::load_cache(list, path) {
+            return bvh;
+        }
+        let bvh = Self::new(list);
+        // a cache that can't be written only costs a rebuild on the next run
+        let _ = bvh.save_cache(list, path);
+        bvh
+    }
+
+    pub fn save_cache(&self, list: &[Arc<Box<dyn Hittable>>], path: &Path) -> io::Result<()> {
+        let indices: HashMap<*const Box<dyn Hittable>, u32> = list
+            .iter()
+            .enumerate()
+            .map(|(i, object)| (Arc::as_ptr(object), i as u32))
+            .collect();
+        let mut nodes = Vec::new();
+        self.root.flatten(&indices, &mut nodes);
+        bvhcache::write(path, Self::geometry_hash(list), list.len(), &nodes)
+    }
+
+    pub fn load_cache(list: &[Arc<Box<dyn Hittable>>], path: &Path) -> io::Result<Option<Self>> {
+        if list.is_empty() {
+            return Ok(None);
+        }
+        let nodes = match bvhcache::read(path, Self::geometry_hash(list), list.len())? {
+            Some(nodes) if !nodes.is_empty() => nodes,
+            _ => return Ok(None),
+        };
+        Ok(Node::from_flat(&nodes, 0, list).map(|root| Self { root }))
+    }
+
+    fn geometry_hash(list: &[Arc<Box<dyn Hittable>>]) -> u64 {
+        let bboxes: Vec<AABB> = list.iter().map(|object| object.bounding_box()).collect();
+        bvhcache::geometry_hash(&bboxes)
+    }
+
     fn new_with_mut_slice(objects: &mut [Arc<Box<dyn Hittable>>]) -> Self {
         Self {
             root: Node::new(objects)
@@ -84,25 +121,69 @@ impl Node {
     }
 }
 
+impl Node {
+    fn flatten(&self, indices: &HashMap<*const Box<dyn Hittable>, u32>, nodes: &mut Vec<FlatNode>) -> u32 {
+        let idx = nodes.len();
+        nodes.push(FlatNode {
+            bbox: self.bbox,
+            left: NO_INDEX,
+            right: NO_INDEX,
+            primitive: NO_INDEX,
+        });
+        if let Some(object) = self.hittable.as_ref() {
+            nodes[idx].primitive = indices[&Arc::as_ptr(object)];
+        } else {
+            nodes[idx].left = self.left.as_ref().unwrap().flatten(indices, nodes);
+            nodes[idx].right = self.right.as_ref().unwrap().flatten(indices, nodes);
+        }
+        idx as u32
+    }
+
+    // Nodes are stored in pre-order, so children always come after their parent.
+    // Anything else means the file is corrupted and we give up on it.
+    fn from_flat(
+        nodes: &[FlatNode],
+        idx: usize,
+        list: &[Arc<Box<dyn Hittable>>],
+    ) -> Option<Arc<Self>> {
+        let node = nodes.get(idx)?;
+        if node.primitive != NO_INDEX {
+            let object = list.get(node.primitive as usize)?.clone();
+            return Some(Arc::new(Self {
+                left: None,
+                right: None,
+                hittable: Some(object),
+                bbox: node.bbox,
+            }));
+        }
+        let (left, right) = (node.left as usize, node.right as usize);
+        if left <= idx || right <= idx {
+            return None;
+        }
+        Some(Arc::new(Self {
+            left: Some(Self::from_flat(nodes, left, list)?),
+            right: Some(Self::from_flat(nodes, right, list)?),
+            hittable: None,
+            bbox: node.bbox,
+        }))
+    }
+}
+
 impl Hittable for Node {
     fn hit(&self, ray: &Ray, t_range: Range<Float>) -> Option<HitRecord> {
+        stats::count_node_visit();
         if !self.bbox.intersect(ray, t_range.clone()) {
             return None;
         }
 
         if let Some(object) = self.hittable.as_ref() {
+            stats::count_primitive_test();
             object.hit(ray, t_range)
         } 
         else if let Some(hit_record) = self.left.as_ref().unwrap().hit(ray, t_range.clone()) {
-            if let Some(hit_record) = self.right.as_ref().unwrap().hit(ray, t_range.start..hit_record.t) {
-                Some(hit_record)
-            } else {
-                Some(hit_record)
-            }
-        } else if let Some(hit_record) = self.right.as_ref().unwrap().hit(ray, t_range) {
-            Some(hit_record)
+            Some(self.right.as_ref().unwrap().hit(ray, t_range.start..hit_record.t).unwrap_or(hit_record))
         } else {
-            None
+            self.right.as_ref().unwrap().hit(ray, t_range)
         }
     }
 
@@ -111,5 +192,54 @@ impl Hittable for Node {
     }
 }
 
+impl Accelerator for BVH {}
+
 unsafe impl Send for BVH {}
-unsafe impl Sync for BVH {}
\ No newline at end of file
+unsafe impl Sync for BVH {}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use crate::{hittable::sphere::Sphere, material::{lambertian::Lambertian, Material}, math::vec3::Vec3};
+
+    use super::*;
+
+    fn dummy_objects(offset: Float) -> Vec<Arc<Box<dyn Hittable>>> {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::zero())));
+        (0..20).map(|i| {
+            let object: Box<dyn Hittable> = Box::new(Sphere::new(
+                Vec3::new(i as Float * 1.5 + offset, (i % 3) as Float, -(i % 5) as Float),
+                0.5,
+                material.clone(),
+            ));
+            Arc::new(object)
+        }).collect()
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let path = temp_dir().join(format!("tiny-raytracer-bvh-roundtrip-{}.bvh", std::process::id()));
+        let objects = dummy_objects(0.0);
+        let bvh = BVH::new(&objects);
+        bvh.save_cache(&objects, &path).expect("failed to write bvh cache");
+
+        let loaded = BVH::load_cache(&objects, &path)
+            .expect("failed to read bvh cache")
+            .expect("expected a valid cache");
+        for i in 0..20 {
+            let ray = Ray::new(
+                Vec3::new(i as Float * 1.5, (i % 3) as Float, 10.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            );
+            let expected = bvh.hit(&ray, 0.001..Float::INFINITY).map(|rec| rec.t);
+            let actual = loaded.hit(&ray, 0.001..Float::INFINITY).map(|rec| rec.t);
+            assert!(expected.is_some());
+            assert_eq!(expected, actual);
+        }
+
+        let moved = dummy_objects(0.1);
+        assert!(BVH::load_cache(&moved, &path).expect("failed to read bvh cache").is_none());
+        std::fs::remove_file(&path).ok();
+    }
+}
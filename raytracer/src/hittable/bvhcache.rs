@@ -0,0 +1,129 @@
+use std::{fs, io::{self, ErrorKind}, path::Path};
+
+use crate::{math::vec3::Vec3, Float};
+
+use super::aabb::AABB;
+
+const MAGIC: &[u8; 8] = b"TRTBVH\0\0";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 8 + 4 + 8 + 4 + 4;
+const NODE_SIZE: usize = 6 * 4 + 3 * 4;
+
+pub(super) const NO_INDEX: u32 = u32::MAX;
+
+#[derive(Clone, Copy)]
+pub(super) struct FlatNode {
+    pub bbox: AABB,
+    pub left: u32,
+    pub right: u32,
+    pub primitive: u32,
+}
+
+// The BVH build only looks at the primitive bounding boxes,
+// so they are all the geometry content the cache has to be keyed on.
+pub(super) fn geometry_hash(bboxes: &[AABB]) -> u64 {
+    // FNV-1a, stable across runs and toolchains unlike std's DefaultHasher
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+    feed(&(bboxes.len() as u64).to_le_bytes());
+    for bbox in bboxes {
+        for v in [bbox.min(), bbox.max()] {
+            for i in 0..3 {
+                feed(&v[i].to_le_bytes());
+            }
+        }
+    }
+    hash
+}
+
+pub(super) fn write(path: &Path, hash: u64, num_primitives: usize, nodes: &[FlatNode]) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + nodes.len() * NODE_SIZE);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&hash.to_le_bytes());
+    bytes.extend_from_slice(&(num_primitives as u32).to_le_bytes());
+    bytes.extend_from_slice(&(nodes.len() as u32).to_le_bytes());
+    for node in nodes {
+        for v in [node.bbox.min(), node.bbox.max()] {
+            for i in 0..3 {
+                bytes.extend_from_slice(&v[i].to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&node.left.to_le_bytes());
+        bytes.extend_from_slice(&node.right.to_le_bytes());
+        bytes.extend_from_slice(&node.primitive.to_le_bytes());
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, bytes)
+}
+
+// Returns None when the file is from another format version
+// or was built for different geometry, so the caller can rebuild it.
+pub(super) fn read(path: &Path, hash: u64, num_primitives: usize) -> io::Result<Option<Vec<FlatNode>>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    if bytes.len() < HEADER_SIZE || &bytes[0..8] != MAGIC {
+        return Err(io::Error::new(ErrorKind::InvalidData, "not a bvh cache file"));
+    }
+
+    let mut reader = Reader { bytes: &bytes, offset: 8 };
+    if reader.u32() != VERSION || reader.u64() != hash || reader.u32() as usize != num_primitives {
+        return Ok(None);
+    }
+    let num_nodes = reader.u32() as usize;
+    if bytes.len() != HEADER_SIZE + num_nodes * NODE_SIZE {
+        return Err(io::Error::new(ErrorKind::InvalidData, "truncated bvh cache file"));
+    }
+
+    let nodes = (0..num_nodes).map(|_| {
+        let min = Vec3::new(reader.float(), reader.float(), reader.float());
+        let max = Vec3::new(reader.float(), reader.float(), reader.float());
+        FlatNode {
+            bbox: AABB::from_bounds(min, max),
+            left: reader.u32(),
+            right: reader.u32(),
+            primitive: reader.u32(),
+        }
+    }).collect();
+    Ok(Some(nodes))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(&self.bytes[self.offset..self.offset + N]);
+        self.offset += N;
+        buf
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    fn float(&mut self) -> Float {
+        Float::from_le_bytes(self.take())
+    }
+}
@@ -0,0 +1,146 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{hittable::{HitRecord, Hittable}, math::vec3::Vec3, ray::Ray, Float};
+
+use super::{accelerator::Accelerator, aabb::AABB, stats};
+
+const MAX_RESOLUTION: usize = 128;
+// target number of objects per cell
+const DENSITY: Float = 3.0;
+
+// Uniform grid, best suited to many similarly sized objects
+pub struct Grid {
+    objects: Vec<Arc<Box<dyn Hittable>>>,
+    cells: Vec<Vec<u32>>,
+    resolution: [usize; 3],
+    cell_size: Vec3,
+    bbox: AABB,
+}
+
+impl Grid {
+    pub fn new(list: &[Arc<Box<dyn Hittable>>]) -> Self {
+        let objects = list.to_vec();
+        let bbox = objects.iter()
+            .map(|object| object.bounding_box())
+            .reduce(AABB::merge)
+            .unwrap_or_default();
+
+        let extent = bbox.max() - bbox.min();
+        let volume = extent.x * extent.y * extent.z;
+        let cells_per_unit = if volume > 0.0 {
+            (DENSITY * objects.len() as Float / volume).cbrt()
+        } else {
+            Float::from(0.0)
+        };
+        let mut resolution = [1usize; 3];
+        let mut cell_size = extent;
+        for i in 0..3 {
+            resolution[i] = ((extent[i] * cells_per_unit).round() as usize).clamp(1, MAX_RESOLUTION);
+            cell_size[i] = extent[i] / resolution[i] as Float;
+        }
+
+        let mut grid = Self {
+            objects,
+            cells: vec![Vec::new(); resolution[0] * resolution[1] * resolution[2]],
+            resolution,
+            cell_size,
+            bbox,
+        };
+        for (idx, object) in grid.objects.iter().enumerate() {
+            let object_bbox = object.bounding_box();
+            let lo = grid.cell_of(object_bbox.min());
+            let hi = grid.cell_of(object_bbox.max());
+            for z in lo[2]..=hi[2] {
+                for y in lo[1]..=hi[1] {
+                    for x in lo[0]..=hi[0] {
+                        let cell = grid.cell_index([x, y, z]);
+                        grid.cells[cell].push(idx as u32);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    fn cell_of(&self, p: Vec3) -> [usize; 3] {
+        let mut cell = [0usize; 3];
+        for i in 0..3 {
+            let offset = (p[i] - self.bbox.min()[i]) / self.cell_size[i];
+            cell[i] = if offset.is_finite() {
+                (offset.max(0.0) as usize).min(self.resolution[i] - 1)
+            } else {
+                0
+            };
+        }
+        cell
+    }
+
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+}
+
+impl Hittable for Grid {
+    fn hit(&self, ray: &Ray, t_range: Range<Float>) -> Option<HitRecord> {
+        let clipped = self.bbox.intersect_range(ray, t_range.clone())?;
+
+        // 3D DDA walk (Amanatides & Woo) through the cells the ray crosses
+        let o = ray.origin();
+        let d = ray.direction();
+        let mut cell = self.cell_of(ray.at(clipped.start));
+        let mut step = [0isize; 3];
+        let mut t_next = [Float::INFINITY; 3];
+        let mut t_delta = [Float::INFINITY; 3];
+        for i in 0..3 {
+            let cell_min = self.bbox.min()[i] + cell[i] as Float * self.cell_size[i];
+            if d[i] > 0.0 {
+                step[i] = 1;
+                t_next[i] = (cell_min + self.cell_size[i] - o[i]) / d[i];
+                t_delta[i] = self.cell_size[i] / d[i];
+            } else if d[i] < 0.0 {
+                step[i] = -1;
+                t_next[i] = (cell_min - o[i]) / d[i];
+                t_delta[i] = -self.cell_size[i] / d[i];
+            }
+        }
+
+        let mut closest = t_range.end;
+        let mut hit_record = None;
+        loop {
+            stats::count_node_visit();
+            for idx in &self.cells[self.cell_index(cell)] {
+                stats::count_primitive_test();
+                if let Some(rec) = self.objects[*idx as usize].hit(ray, t_range.start..closest) {
+                    closest = rec.t;
+                    hit_record = Some(rec);
+                }
+            }
+
+            let axis = if t_next[0] < t_next[1] {
+                if t_next[0] < t_next[2] { 0 } else { 2 }
+            } else {
+                if t_next[1] < t_next[2] { 1 } else { 2 }
+            };
+            // objects span several cells, so a hit only counts once the walk has passed it
+            if closest <= t_next[axis] || t_next[axis] > clipped.end {
+                break;
+            }
+            let next = cell[axis] as isize + step[axis];
+            if next < 0 || next >= self.resolution[axis] as isize {
+                break;
+            }
+            cell[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+        hit_record
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+impl Accelerator for Grid {}
+
+unsafe impl Send for Grid {}
+unsafe impl Sync for Grid {}
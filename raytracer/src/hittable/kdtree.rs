@@ -0,0 +1,204 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{hittable::{HitRecord, Hittable}, math::vec3::Vec3, ray::Ray, Float};
+
+use super::{accelerator::Accelerator, aabb::AABB, stats};
+
+const TRAVERSAL_COST: Float = 1.0;
+const INTERSECTION_COST: Float = 80.0;
+const EMPTY_BONUS: Float = 0.5;
+const MAX_BAD_REFINES: usize = 3;
+const MAX_LEAF_OBJECTS: usize = 1;
+
+enum KdNode {
+    Interior {
+        axis: usize,
+        split: Float,
+        above: usize,
+    },
+    Leaf {
+        objects: Vec<u32>,
+    },
+}
+
+#[derive(Clone, Copy)]
+struct Edge {
+    position: Float,
+    object: u32,
+    is_start: bool,
+}
+
+// kd-tree built with the surface area heuristic.
+// The below child of an interior node is always stored right after it.
+pub struct KdTree {
+    objects: Vec<Arc<Box<dyn Hittable>>>,
+    nodes: Vec<KdNode>,
+    bbox: AABB,
+}
+
+impl KdTree {
+    pub fn new(list: &[Arc<Box<dyn Hittable>>]) -> Self {
+        let objects = list.to_vec();
+        let bboxes: Vec<AABB> = objects.iter().map(|object| object.bounding_box()).collect();
+        let bbox = bboxes.iter().copied().reduce(AABB::merge).unwrap_or_default();
+        let max_depth = (8.0 + 1.3 * (objects.len().max(1) as Float).log2()).round() as usize;
+
+        let mut tree = Self {
+            objects,
+            nodes: Vec::new(),
+            bbox,
+        };
+        let indices: Vec<u32> = (0..bboxes.len() as u32).collect();
+        tree.build(&bboxes, bbox, indices, max_depth, 0);
+        tree
+    }
+
+    fn build(&mut self, bboxes: &[AABB], node_bbox: AABB, indices: Vec<u32>, depth: usize, bad_refines: usize) {
+        if indices.len() <= MAX_LEAF_OBJECTS || depth == 0 {
+            self.nodes.push(KdNode::Leaf { objects: indices });
+            return;
+        }
+
+        let leaf_cost = INTERSECTION_COST * indices.len() as Float;
+        let Some((axis, split, cost)) = Self::find_split(bboxes, node_bbox, &indices) else {
+            self.nodes.push(KdNode::Leaf { objects: indices });
+            return;
+        };
+        let bad_refines = if cost > leaf_cost { bad_refines + 1 } else { bad_refines };
+        if (cost > 4.0 * leaf_cost && indices.len() < 16) || bad_refines == MAX_BAD_REFINES {
+            self.nodes.push(KdNode::Leaf { objects: indices });
+            return;
+        }
+
+        let below: Vec<u32> = indices.iter().copied()
+            .filter(|i| bboxes[*i as usize].min()[axis] < split)
+            .collect();
+        let above: Vec<u32> = indices.iter().copied()
+            .filter(|i| bboxes[*i as usize].max()[axis] > split)
+            .collect();
+
+        let mut below_max = node_bbox.max();
+        below_max[axis] = split;
+        let mut above_min = node_bbox.min();
+        above_min[axis] = split;
+
+        let idx = self.nodes.len();
+        self.nodes.push(KdNode::Interior { axis, split, above: 0 });
+        self.build(bboxes, AABB::from_bounds(node_bbox.min(), below_max), below, depth - 1, bad_refines);
+        let above_idx = self.nodes.len();
+        if let KdNode::Interior { above, .. } = &mut self.nodes[idx] {
+            *above = above_idx;
+        }
+        self.build(bboxes, AABB::from_bounds(above_min, node_bbox.max()), above, depth - 1, bad_refines);
+    }
+
+    fn find_split(bboxes: &[AABB], node_bbox: AABB, indices: &[u32]) -> Option<(usize, Float, Float)> {
+        let extent = node_bbox.max() - node_bbox.min();
+        let inv_area = Float::from(1.0) / node_bbox.surface_area();
+        let mut best: Option<(usize, Float, Float)> = None;
+
+        for axis in 0..3 {
+            let mut edges: Vec<Edge> = Vec::with_capacity(indices.len() * 2);
+            for i in indices {
+                let bbox = bboxes[*i as usize];
+                edges.push(Edge { position: bbox.min()[axis], object: *i, is_start: true });
+                edges.push(Edge { position: bbox.max()[axis], object: *i, is_start: false });
+            }
+            // ends sort before starts at the same position so touching objects separate cleanly
+            edges.sort_by(|a, b| {
+                a.position.total_cmp(&b.position).then(a.is_start.cmp(&b.is_start))
+            });
+
+            let (other0, other1) = ((axis + 1) % 3, (axis + 2) % 3);
+            let mut num_below = 0usize;
+            let mut num_above = indices.len();
+            for edge in &edges {
+                if !edge.is_start {
+                    num_above -= 1;
+                }
+                let t = edge.position;
+                if node_bbox.min()[axis] < t && t < node_bbox.max()[axis] {
+                    let below_area = 2.0 * (extent[other0] * extent[other1]
+                        + (t - node_bbox.min()[axis]) * (extent[other0] + extent[other1]));
+                    let above_area = 2.0 * (extent[other0] * extent[other1]
+                        + (node_bbox.max()[axis] - t) * (extent[other0] + extent[other1]));
+                    let bonus = if num_below == 0 || num_above == 0 { EMPTY_BONUS } else { 0.0 };
+                    let cost = TRAVERSAL_COST + INTERSECTION_COST * (1.0 - bonus)
+                        * (below_area * inv_area * num_below as Float + above_area * inv_area * num_above as Float);
+                    if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                        best = Some((axis, t, cost));
+                    }
+                }
+                if edge.is_start {
+                    num_below += 1;
+                }
+            }
+        }
+        best
+    }
+}
+
+impl Hittable for KdTree {
+    fn hit(&self, ray: &Ray, t_range: Range<Float>) -> Option<HitRecord> {
+        let clipped = self.bbox.intersect_range(ray, t_range.clone())?;
+        let o = ray.origin();
+        let inv_d = Vec3::new(1.0, 1.0, 1.0) / ray.direction();
+
+        let mut closest = t_range.end;
+        let mut hit_record = None;
+        let mut stack: Vec<(usize, Float, Float)> = Vec::with_capacity(64);
+        let (mut node, mut t_min, mut t_max) = (0usize, clipped.start, clipped.end);
+        loop {
+            if closest < t_min {
+                break;
+            }
+            stats::count_node_visit();
+            match &self.nodes[node] {
+                KdNode::Interior { axis, split, above } => {
+                    let (axis, split, above) = (*axis, *split, *above);
+                    let t_split = (split - o[axis]) * inv_d[axis];
+                    let below_first = o[axis] < split || (o[axis] == split && inv_d[axis] <= 0.0);
+                    let (first, second) = if below_first { (node + 1, above) } else { (above, node + 1) };
+
+                    if t_split > t_max || t_split <= 0.0 || t_split.is_nan() {
+                        node = first;
+                    } else if t_split < t_min {
+                        node = second;
+                    } else {
+                        stack.push((second, t_split, t_max));
+                        node = first;
+                        t_max = t_split;
+                    }
+                    continue;
+                }
+                KdNode::Leaf { objects } => {
+                    for idx in objects {
+                        stats::count_primitive_test();
+                        if let Some(rec) = self.objects[*idx as usize].hit(ray, t_range.start..closest) {
+                            closest = rec.t;
+                            hit_record = Some(rec);
+                        }
+                    }
+                }
+            }
+
+            if let Some((next, next_min, next_max)) = stack.pop() {
+                node = next;
+                t_min = next_min;
+                t_max = next_max;
+            } else {
+                break;
+            }
+        }
+        hit_record
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+impl Accelerator for KdTree {}
+
+unsafe impl Send for KdTree {}
+unsafe impl Sync for KdTree {}
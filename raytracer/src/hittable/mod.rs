@@ -2,26 +2,68 @@ use std::{ops::Range, sync::Arc};
 
 use as_any::AsAny;
 
-use crate::{material::Material, math::vec3::Vec3, ray::Ray, Float};
+use crate::{material::Material, math::{onb::Onb, vec3::Vec3}, ray::Ray, Float};
 
 pub mod world;
 pub mod sphere;
 pub mod quad;
 pub mod aabb;
 pub mod bvh;
+mod bvhcache;
+pub mod stats;
+pub mod accelerator;
+pub mod grid;
+pub mod kdtree;
 
 pub trait Hittable: AsAny {
     fn hit(&self, ray: &Ray, t_range: Range<Float>) -> Option<HitRecord>;
 
     fn bounding_box(&self) -> aabb::AABB;
+
+    // Whether the surface emits light and should be sampled directly
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    // Light sampling: a point on the surface as seen from origin, with the
+    // solid angle density of the direction towards it
+    fn sample_point(&self, _origin: &Vec3) -> Option<(Vec3, Float)> {
+        None
+    }
+
+    // Solid angle density with which sample_point picks direction from origin
+    fn pdf_value(&self, _origin: &Vec3, _direction: &Vec3) -> Float {
+        0.0
+    }
+
+    // Light tracing: a point uniform over the surface and the outward normal
+    // there; its area density is 1 / area()
+    fn sample_surface(&self) -> Option<(Vec3, Vec3)> {
+        None
+    }
+
+    // Surface area, used to estimate how much light an emitter gives off
+    fn area(&self) -> Float {
+        0.0
+    }
 }
 
+#[derive(Clone)]
 pub struct HitRecord {
     pub t: Float,
     pub point: Vec3,
+    // shading normal, facing the incoming ray; normal and bump maps may tilt it
     pub normal: Vec3,
+    // true surface orientation, facing the incoming ray
+    pub geometric_normal: Vec3,
+    // surface derivative along u, zero when the geometry doesn't provide one
+    pub tangent: Vec3,
+    pub u: Float,
+    pub v: Float,
     pub front_face: bool,
     pub material: Arc<Box<dyn Material>>,
+    // wavelength (nm) the path has been narrowed to by a dispersive bounce
+    pub wavelength: Option<Float>,
 }
 
 impl HitRecord {
@@ -29,6 +71,7 @@ impl HitRecord {
         ray: &Ray,
         t: Float,
         outward_normal: Vec3,
+        (u, v): (Float, Float),
         material: Arc<Box<dyn Material>>,
     ) -> HitRecord {
         let point = ray.at(t);
@@ -42,8 +85,23 @@ impl HitRecord {
             t,
             point,
             normal,
+            geometric_normal: normal,
+            tangent: Vec3::zero(),
+            u,
+            v,
             front_face,
             material,
+            wavelength: None,
         }
     }
-}
\ No newline at end of file
+
+    pub fn with_tangent(mut self, tangent: Vec3) -> HitRecord {
+        self.tangent = tangent;
+        self
+    }
+
+    // Local frame for BSDF evaluation, z along the normal and x along the tangent
+    pub fn shading_frame(&self) -> Onb {
+        Onb::new_with_tangent(self.normal, self.tangent)
+    }
+}
@@ -1,6 +1,6 @@
 use std::{ops::Range, sync::Arc};
 
-use crate::{material::Material, math::vec3::Vec3, ray::Ray, Float};
+use crate::{material::Material, math::vec3::Vec3, ray::Ray, utils::random::random, Float};
 
 use super::{aabb::AABB, HitRecord, Hittable};
 
@@ -27,6 +27,16 @@ impl Quad {
         let d = n.dot(&corner);
         Self { corner, u, v, n, w, d, bbox, material } 
     }
+
+    // Converts the uniform area density to solid angle as seen along direction
+    fn solid_angle_pdf(&self, direction: &Vec3, distance_squared: Float) -> Float {
+        let area = self.n.length();
+        let cos = direction.dot(&self.n).abs() / (direction.length() * area);
+        if cos < 1e-6 {
+            return 0.0;
+        }
+        distance_squared / (cos * area)
+    }
 }
 
 impl Hittable for Quad {
@@ -38,13 +48,15 @@ impl Hittable for Quad {
             let p = ray.at(t) - self.corner;
             let planar_x = p.cross(&self.v).dot(&self.w);
             let planar_y = self.u.cross(&p).dot(&self.w);
-            if (0.0..1.0).contains(&planar_x) && (0.0..1.0).contains(&planar_y) {
+            if (0.0..1.0).contains(&planar_x) && (0.0..1.0).contains(&planar_y)
+                && self.material.is_opaque(planar_x, planar_y, &ray.at(t)) {
                 Some(HitRecord::new(
                     ray,
                     t,
                     self.n,
+                    (planar_x, planar_y),
                     self.material.clone(),
-                ))
+                ).with_tangent(self.u))
             } else {
                 None
             }
@@ -56,6 +68,36 @@ impl Hittable for Quad {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn sample_point(&self, origin: &Vec3) -> Option<(Vec3, Float)> {
+        let point = self.corner + random::<Float>() * self.u + random::<Float>() * self.v;
+        let direction = point - *origin;
+        let pdf = self.solid_angle_pdf(&direction, direction.squared_length());
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((point, pdf))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> Float {
+        let Some(rec) = self.hit(&Ray::new(*origin, *direction), 0.001..Float::INFINITY) else {
+            return 0.0;
+        };
+        self.solid_angle_pdf(direction, (rec.point - *origin).squared_length())
+    }
+
+    fn sample_surface(&self) -> Option<(Vec3, Vec3)> {
+        let point = self.corner + random::<Float>() * self.u + random::<Float>() * self.v;
+        Some((point, self.n.normalized()))
+    }
+
+    fn area(&self) -> Float {
+        self.n.length()
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 use std::{ops::Range, sync::Arc};
 
-use crate::{material::Material, math::vec3::Vec3, ray::Ray, Float};
+use crate::{material::Material, math::{onb::Onb, vec3::Vec3}, ray::Ray, Float, FloatConsts};
 
 use super::{aabb::AABB, HitRecord, Hittable};
 
@@ -23,6 +23,34 @@ impl Sphere {
             material: material,
         }
     }
+
+    // u goes around the y axis starting from -x, v from the south pole to the north pole
+    pub fn uv(outward_normal: Vec3) -> (Float, Float) {
+        let theta = (-outward_normal.y).clamp(-1.0, 1.0).acos();
+        let phi = (-outward_normal.z).atan2(outward_normal.x) + FloatConsts::PI;
+        (phi / (2.0 * FloatConsts::PI), theta / FloatConsts::PI)
+    }
+
+    // 1 - cos of the half angle the sphere subtends from origin,
+    // None when origin is inside
+    fn cone_extent(&self, origin: &Vec3) -> Option<Float> {
+        let sin2 = self.radius * self.radius / (self.center - *origin).squared_length();
+        if sin2 >= 1.0 {
+            return None;
+        }
+        // the series expansion keeps small, distant spheres from rounding to zero
+        Some(if sin2 < 1e-4 { 0.5 * sin2 } else { 1.0 - (1.0 - sin2).sqrt() })
+    }
+
+    fn area_pdf(&self, origin: &Vec3, point: &Vec3) -> Float {
+        let direction = *point - *origin;
+        let outward_normal = (*point - self.center) / self.radius;
+        let cos = outward_normal.dot(&direction).abs() / direction.length();
+        if cos < 1e-6 {
+            return 0.0;
+        }
+        direction.squared_length() / (cos * 4.0 * FloatConsts::PI * self.radius * self.radius)
+    }
 }
 
 impl Hittable for Sphere {
@@ -36,26 +64,68 @@ impl Hittable for Sphere {
             return None;
         }
         let sqrtd = discriminant.sqrt();
-        let mut t = (-half_b - sqrtd) / a;
-        if !t_range.contains(&t) {
-            t = (-half_b + sqrtd) / a;
+        // the far root is still visible through a cut out near one
+        for t in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
             if !t_range.contains(&t) {
-                return None;
+                continue;
             }
+            let p = ray.at(t);
+            // a negative radius only sizes the sphere, the normal still points outward
+            let outward_normal = (p - self.center) / self.radius.abs();
+            let (u, v) = Self::uv(outward_normal);
+            if !self.material.is_opaque(u, v, &p) {
+                continue;
+            }
+            return Some(HitRecord::new(
+                ray,
+                t,
+                outward_normal,
+                (u, v),
+                self.material.clone(),
+            ).with_tangent(Vec3::new(outward_normal.z, 0.0, -outward_normal.x)));
         }
-
-        let p = ray.at(t);
-        Some(HitRecord::new(
-            ray,
-            t,
-            p - self.center,
-            self.material.clone(),
-        ))
+        None
     }
 
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    // Outside the sphere only the cone of directions it covers is sampled,
+    // inside points are picked uniformly over the area
+    fn sample_point(&self, origin: &Vec3) -> Option<(Vec3, Float)> {
+        let Some(extent) = self.cone_extent(origin) else {
+            let point = self.center + self.radius * Vec3::new_random_unit_vector();
+            let pdf = self.area_pdf(origin, &point);
+            return if pdf > 0.0 { Some((point, pdf)) } else { None };
+        };
+        let direction = Onb::new(self.center - *origin).to_world(Vec3::new_random_in_cone(extent));
+        let rec = self.hit(&Ray::new(*origin, direction), 0.001..Float::INFINITY)?;
+        Some((rec.point, 1.0 / (2.0 * FloatConsts::PI * extent)))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> Float {
+        let Some(rec) = self.hit(&Ray::new(*origin, *direction), 0.001..Float::INFINITY) else {
+            return 0.0;
+        };
+        match self.cone_extent(origin) {
+            Some(extent) => 1.0 / (2.0 * FloatConsts::PI * extent),
+            None => self.area_pdf(origin, &rec.point),
+        }
+    }
+
+    fn sample_surface(&self) -> Option<(Vec3, Vec3)> {
+        let normal = Vec3::new_random_unit_vector();
+        Some((self.center + self.radius.abs() * normal, normal))
+    }
+
+    fn area(&self) -> Float {
+        4.0 * FloatConsts::PI * self.radius * self.radius
+    }
 }
 
 #[cfg(test)]
@@ -97,5 +167,12 @@ mod tests {
         let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, -1.0, -1.0));
         let hit = sphere.hit(&ray, 0.0..INFINITY);
         assert!(hit.is_none());
+
+        // a negative radius doesn't turn the surface inside out
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -1.0), -0.5, dummy_mat);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = sphere.hit(&ray, 0.0..INFINITY).expect("Expected hit, but got None");
+        assert!(hit_record.front_face);
+        assert_eq!(hit_record.normal, Vec3::new(0.0, 0.0, 1.0));
     }
 }
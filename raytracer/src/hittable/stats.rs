@@ -0,0 +1,84 @@
+use std::{cell::Cell, fmt::Display};
+
+// Traversal counters live per thread: a single ray is always traced
+// on one thread, so the sampler can reset them before a ray and read them after.
+thread_local! {
+    static NODES_VISITED: Cell<u64> = const { Cell::new(0) };
+    static PRIMITIVE_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn count_node_visit() {
+    NODES_VISITED.with(|c| c.set(c.get() + 1));
+}
+
+pub fn count_primitive_test() {
+    PRIMITIVE_TESTS.with(|c| c.set(c.get() + 1));
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RayCost {
+    pub nodes_visited: u64,
+    pub primitive_tests: u64,
+}
+
+impl RayCost {
+    pub fn total(&self) -> u64 {
+        self.nodes_visited + self.primitive_tests
+    }
+}
+
+pub fn take_ray_cost() -> RayCost {
+    RayCost {
+        nodes_visited: NODES_VISITED.with(|c| c.replace(0)),
+        primitive_tests: PRIMITIVE_TESTS.with(|c| c.replace(0)),
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TraversalStats {
+    pub rays: u64,
+    pub nodes_visited: u64,
+    pub primitive_tests: u64,
+    pub max_nodes_visited: u64,
+    pub max_primitive_tests: u64,
+}
+
+impl TraversalStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, cost: RayCost) {
+        self.rays += 1;
+        self.nodes_visited += cost.nodes_visited;
+        self.primitive_tests += cost.primitive_tests;
+        self.max_nodes_visited = self.max_nodes_visited.max(cost.nodes_visited);
+        self.max_primitive_tests = self.max_primitive_tests.max(cost.primitive_tests);
+    }
+
+    pub fn merge(a: TraversalStats, b: TraversalStats) -> Self {
+        Self {
+            rays: a.rays + b.rays,
+            nodes_visited: a.nodes_visited + b.nodes_visited,
+            primitive_tests: a.primitive_tests + b.primitive_tests,
+            max_nodes_visited: a.max_nodes_visited.max(b.max_nodes_visited),
+            max_primitive_tests: a.max_primitive_tests.max(b.max_primitive_tests),
+        }
+    }
+
+    pub fn avg_nodes_visited(&self) -> f64 {
+        if self.rays == 0 { 0.0 } else { self.nodes_visited as f64 / self.rays as f64 }
+    }
+
+    pub fn avg_primitive_tests(&self) -> f64 {
+        if self.rays == 0 { 0.0 } else { self.primitive_tests as f64 / self.rays as f64 }
+    }
+}
+
+impl Display for TraversalStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "rays traced:     {}", self.rays)?;
+        writeln!(f, "nodes visited:   {:.2} avg, {} max per ray", self.avg_nodes_visited(), self.max_nodes_visited)?;
+        write!(f, "primitive tests: {:.2} avg, {} max per ray", self.avg_primitive_tests(), self.max_primitive_tests)
+    }
+}
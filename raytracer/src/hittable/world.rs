@@ -1,27 +1,43 @@
-use std::{collections::HashMap, ops::Range, sync::Arc};
+use std::{collections::{BTreeMap, HashMap}, io::{self, ErrorKind}, ops::Range, path::{Path, PathBuf}, sync::Arc};
 
 use as_any::Downcast;
 use metal::Device;
 
-use crate::{hittable::{HitRecord, Hittable}, material::Material, ray::Ray, renderer::sampler::metal::geometry::{quad::MetalQuadGeometry, sphere::MetalSphereGeometry, MetalGeometry}, Float};
+use crate::{hittable::{HitRecord, Hittable}, lighting::{area::AreaLight, Emitter, Lights}, material::{library::{read_library, write_library}, Material}, ray::Ray, renderer::sampler::metal::geometry::{quad::MetalQuadGeometry, sphere::MetalSphereGeometry, MetalGeometry}, Float};
 
-use super::{aabb::AABB, bvh::BVH, quad::Quad, sphere::Sphere};
+use super::{accelerator::{Accelerator, AcceleratorType}, aabb::AABB, bvh::BVH, grid::Grid, kdtree::KdTree, quad::Quad, sphere::Sphere};
 
 pub struct World {
     geometries: Vec<Arc<Box<dyn Hittable>>>,
+    // emissive subset of geometries, sampled directly as lights
+    emitters: Vec<Arc<Box<dyn Hittable>>>,
+    // lights without geometry: point, spot and directional
+    lights: Vec<Arc<dyn Emitter>>,
     materials: HashMap<String, Arc<Box<dyn Material>>>,
+    bvh_cache_path: Option<PathBuf>,
 }
 
 impl World {
     pub fn new() -> Self {
         Self {
             geometries: Vec::new(),
+            emitters: Vec::new(),
+            lights: Vec::new(),
             materials: HashMap::new(),
+            bvh_cache_path: None,
         }
     }
 
     pub fn add_geometry(&mut self, geometry: Box<dyn Hittable>) {
-        self.geometries.push(Arc::new(geometry));
+        let geometry = Arc::new(geometry);
+        if geometry.is_emissive() {
+            self.emitters.push(geometry.clone());
+        }
+        self.geometries.push(geometry);
+    }
+
+    pub fn add_light(&mut self, light: Box<dyn Emitter>) {
+        self.lights.push(Arc::from(light));
     }
 
     pub fn add_material(&mut self, name: &str, material: Box<dyn Material>) {
@@ -40,8 +56,58 @@ impl World {
         }
     }
 
+    // Adds every material of a library file to the material table
+    pub fn load_materials(&mut self, path: &Path) -> io::Result<()> {
+        let library = read_library(path)?;
+        if let Some(name) = library.keys().find(|name| self.materials.contains_key(*name)) {
+            return Err(io::Error::new(ErrorKind::AlreadyExists, format!("{} key is already in the material table", name)));
+        }
+        for (name, desc) in library {
+            let material = desc.build()?;
+            self.add_material(&name, material);
+        }
+        Ok(())
+    }
+
+    // Writes the material table as a library file. Fails when a material
+    // (or one of its textures) has no data file form.
+    pub fn save_materials(&self, path: &Path) -> io::Result<()> {
+        let mut library = BTreeMap::new();
+        for (name, material) in &self.materials {
+            let desc = material.describe().ok_or_else(|| {
+                io::Error::new(ErrorKind::Unsupported, format!("material {} can't be written to a library", name))
+            })?;
+            library.insert(name.clone(), desc);
+        }
+        write_library(path, &library)
+    }
+
+    pub fn set_bvh_cache_path(&mut self, path: &Path) {
+        self.bvh_cache_path = Some(path.to_path_buf());
+    }
+
     pub fn get_bvh(&self) -> BVH {
-        BVH::new(&self.geometries)
+        if let Some(path) = self.bvh_cache_path.as_ref() {
+            BVH::new_cached(&self.geometries, path)
+        } else {
+            BVH::new(&self.geometries)
+        }
+    }
+
+    pub fn get_accelerator(&self, accelerator_type: AcceleratorType) -> Arc<dyn Accelerator> {
+        match accelerator_type {
+            AcceleratorType::BVH => Arc::new(self.get_bvh()),
+            AcceleratorType::Grid => Arc::new(Grid::new(&self.geometries)),
+            AcceleratorType::KdTree => Arc::new(KdTree::new(&self.geometries)),
+        }
+    }
+
+    pub fn get_lights(&self) -> Lights {
+        let mut emitters: Vec<Arc<dyn Emitter>> = self.emitters.iter()
+            .map(|geometry| Arc::new(AreaLight::new(geometry.clone())) as Arc<dyn Emitter>)
+            .collect();
+        emitters.extend(self.lights.iter().cloned());
+        Lights::new(emitters)
     }
 
     pub fn get_geometries<T: Downcast + Clone>(&self) -> Vec<T> {
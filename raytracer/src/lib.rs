@@ -9,6 +9,8 @@ pub mod utils;
 pub mod camera;
 pub mod hittable;
 pub mod material;
+pub mod lighting;
+pub mod texture;
 pub mod math;
 pub mod ray;
 pub mod renderer;
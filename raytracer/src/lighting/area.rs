@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use crate::{hittable::{aabb::AABB, Hittable}, math::{onb::Onb, vec3::Vec3}, ray::Ray, utils::random::random, Float, FloatConsts};
+
+use super::{luminance, EmissionSample, Emitter, LightSample};
+
+// How far in front of the surface emission is looked up from
+const EMISSION_OFFSET: Float = 1e-3;
+
+// Emissive geometry sampled through its surface
+pub struct AreaLight {
+    geometry: Arc<Box<dyn Hittable>>,
+}
+
+impl AreaLight {
+    pub fn new(geometry: Arc<Box<dyn Hittable>>) -> Self {
+        Self { geometry }
+    }
+
+    // Radiance leaving point along direction, through a hit from just in front of it
+    fn emission(&self, point: &Vec3, direction: &Vec3) -> Option<Vec3> {
+        let ray = Ray::new(*point + *direction * EMISSION_OFFSET, -*direction);
+        let rec = self.geometry.hit(&ray, 0.0..2.0 * EMISSION_OFFSET)?;
+        rec.material.emitted(&rec, direction)
+    }
+}
+
+impl Emitter for AreaLight {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let (point, pdf) = self.geometry.sample_point(origin)?;
+        let wi = (point - *origin).normalized();
+        // the hit gives the uv and facing the material needs to emit
+        let rec = self.geometry.hit(&Ray::new(*origin, wi), 0.001..Float::INFINITY)?;
+        let radiance = rec.material.emitted(&rec, &-wi)?;
+        Some(LightSample { wi, distance: rec.t, radiance, normal: rec.geometric_normal, pdf, hittable: true })
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> Float {
+        self.geometry.pdf_value(origin, direction)
+    }
+
+    fn bounds(&self) -> Option<AABB> {
+        Some(self.geometry.bounding_box())
+    }
+
+    // Emission only shows through a hit, so the surface is looked at from
+    // around its bounds and the brightest radiance seen is taken for all of it
+    fn power(&self) -> Float {
+        let bounds = self.geometry.bounding_box();
+        let center = 0.5 * (bounds.min() + bounds.max());
+        let reach = (bounds.max() - bounds.min()).length();
+        let axes = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+        let radiance = axes.iter()
+            .flat_map(|axis| [*axis, -*axis])
+            .filter_map(|axis| self.sample(&(center + axis * reach)))
+            .map(|sample| luminance(&sample.radiance))
+            .fold(0.0, Float::max);
+        radiance * self.geometry.area() * FloatConsts::PI
+    }
+
+    // Both sides are sampled alike, the material decides which of them emit
+    fn sample_emission(&self) -> Option<EmissionSample> {
+        let (point, normal) = self.geometry.sample_surface()?;
+        let side = if random::<Float>() < 0.5 { normal } else { -normal };
+        let local = Vec3::new_random_cosine_direction();
+        let direction = Onb::new(side).to_world(local).normalized();
+        let radiance = self.emission(&point, &direction)?;
+        Some(EmissionSample {
+            point,
+            normal,
+            direction,
+            radiance,
+            pdf_position: 1.0 / self.geometry.area(),
+            pdf_direction: 0.5 * local.z / FloatConsts::PI,
+            hittable: true,
+        })
+    }
+
+    fn pdf_emission(&self, _point: &Vec3, normal: &Vec3, direction: &Vec3) -> (Float, Float) {
+        (1.0 / self.geometry.area(), 0.5 * normal.dot(direction).abs() / FloatConsts::PI)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<Float> {
+        self.geometry.hit(ray, 0.001..Float::INFINITY).map(|rec| rec.t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hittable::{quad::Quad, sphere::Sphere}, material::{light::Light, Material}, utils::random::random, FloatConsts};
+
+    use super::*;
+
+    // Integrating pdf over the sphere of directions must give one
+    fn total_density(light: &AreaLight, origin: &Vec3) -> Float {
+        let n = 200000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            sum += light.pdf(origin, &Vec3::new_random_unit_vector());
+        }
+        sum * 4.0 * FloatConsts::PI / n as Float
+    }
+
+    #[test]
+    fn test_pdf_normalization() {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Light::new(Vec3::new_diagonal(1.0))));
+        let quad = AreaLight::new(Arc::new(Box::new(Quad::new(
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            material.clone(),
+        ))));
+        let sphere = AreaLight::new(Arc::new(Box::new(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 1.0, material))));
+        let origin = Vec3::zero();
+        assert!((total_density(&quad, &origin) - 1.0).abs() < 0.03);
+        assert!((total_density(&sphere, &origin) - 1.0).abs() < 0.03);
+        assert!((total_density(&sphere, &Vec3::new(0.0, 2.2, 0.3)) - 1.0).abs() < 0.03);
+
+        // sampled directions report the same density as pdf()
+        for light in [&quad, &sphere] {
+            for _ in 0..100 {
+                let origin = Vec3::new(random::<Float>() - 0.5, -1.0, random::<Float>() - 0.5);
+                let Some(sample) = light.sample(&origin) else { continue };
+                let pdf = light.pdf(&origin, &sample.wi);
+                assert!((pdf - sample.pdf).abs() <= 1e-3 * pdf, "{} != {}", pdf, sample.pdf);
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use image::ImageResult;
+
+use crate::{hittable::sphere::Sphere, math::vec3::Vec3, texture::{image::{ImageTexture, WrapMode}, Texture}, utils::{distribution::Distribution2D, random::random}, Float, FloatConsts};
+
+use super::{luminance, Emitter, LightSample};
+
+// Direction of image coordinates (x right, y down), the inverse of Sphere::uv
+fn direction(x: Float, y: Float) -> Vec3 {
+    let theta = (1.0 - y) * FloatConsts::PI;
+    let phi = x * 2.0 * FloatConsts::PI - FloatConsts::PI;
+    Vec3::new(theta.sin() * phi.cos(), -theta.cos(), -theta.sin() * phi.sin())
+}
+
+// Light arriving from every direction, read from an equirectangular image:
+// u goes around the y axis and v from the bottom to the top, as on a Sphere.
+// Directions are importance sampled by pixel luminance.
+pub struct EnvironmentLight {
+    image: ImageTexture,
+    distribution: Distribution2D,
+    intensity: Float,
+    // rotation around the y axis, in radians
+    rotation: Float,
+}
+
+impl EnvironmentLight {
+    // Radiance .hdr or OpenEXR .exr files, whose values are linear
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        Ok(Self::new(ImageTexture::open_linear(path)?))
+    }
+
+    pub fn new(image: ImageTexture) -> Self {
+        let image = image.with_wrap_mode(WrapMode::Repeat);
+        let (width, height) = image.size();
+        // rows shrink towards the poles, so they are weighted by sin(theta)
+        let func: Vec<Float> = (0..height).flat_map(|y| {
+            let sin_theta = ((y as Float + 0.5) / height as Float * FloatConsts::PI).sin();
+            let image = &image;
+            (0..width).map(move |x| luminance(&image.texel(x as i64, y as i64)) * sin_theta)
+        }).collect();
+        let distribution = Distribution2D::new(width, height, &func);
+        Self { image, distribution, intensity: 1.0, rotation: 0.0 }
+    }
+
+    // Tabulates an analytic environment given as radiance per direction
+    pub(crate) fn from_fn(width: usize, height: usize, radiance: impl Fn(&Vec3) -> Vec3) -> Self {
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let direction = direction((x as Float + 0.5) / width as Float, (y as Float + 0.5) / height as Float);
+                texels.push(radiance(&direction));
+            }
+        }
+        Self::new(ImageTexture::new(width, height, texels))
+    }
+
+    pub fn with_intensity(mut self, intensity: Float) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    // Turns the environment around the y axis, in degrees
+    pub fn with_rotation(mut self, degrees: Float) -> Self {
+        self.rotation = degrees.to_radians();
+        self
+    }
+
+    fn rotate(&self, direction: &Vec3, angle: Float) -> Vec3 {
+        let (sin, cos) = angle.sin_cos();
+        Vec3::new(cos * direction.x + sin * direction.z, direction.y, -sin * direction.x + cos * direction.z)
+    }
+
+    // Image coordinates (x right, y down, both in [0, 1)) of a world direction
+    fn image_coordinates(&self, direction: &Vec3) -> (Float, Float) {
+        let (u, v) = Sphere::uv(self.rotate(&direction.normalized(), -self.rotation));
+        (u.rem_euclid(1.0), (1.0 - v).clamp(0.0, 1.0))
+    }
+
+    fn direction(&self, x: Float, y: Float) -> Vec3 {
+        self.rotate(&direction(x, y), self.rotation)
+    }
+
+    // Converts the density over the image to solid angle
+    fn solid_angle_pdf(&self, image_pdf: Float, y: Float) -> Float {
+        let sin_theta = (y * FloatConsts::PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        image_pdf / (2.0 * FloatConsts::PI * FloatConsts::PI * sin_theta)
+    }
+}
+
+impl Emitter for EnvironmentLight {
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        let ((x, y), image_pdf) = self.distribution.sample(random(), random());
+        let pdf = self.solid_angle_pdf(image_pdf, y);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let wi = self.direction(x, y);
+        Some(LightSample { wi, distance: Float::INFINITY, radiance: self.radiance(&wi), normal: Vec3::zero(), pdf, hittable: true })
+    }
+
+    fn pdf(&self, _origin: &Vec3, direction: &Vec3) -> Float {
+        let (x, y) = self.image_coordinates(direction);
+        self.solid_angle_pdf(self.distribution.pdf(x, y), y)
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (x, y) = self.image_coordinates(direction);
+        // filtering wraps around horizontally but must not blend the poles together
+        let half_row = 0.5 / self.image.size().1 as Float;
+        let y = y.clamp(half_row, 1.0 - half_row);
+        self.image.value(x, 1.0 - y, &Vec3::zero()) * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_importance_sampling() {
+        // dim environment with one bright pixel
+        let (width, height) = (16, 8);
+        let mut texels = vec![Vec3::new_diagonal(0.1); width * height];
+        texels[2 * width + 11] = Vec3::new_diagonal(500.0);
+        let light = EnvironmentLight::new(ImageTexture::new(width, height, texels)).with_rotation(30.0);
+
+        // the density integrates to one over the sphere
+        let steps = 256;
+        let mut integral = 0.0;
+        for i in 0..steps {
+            let theta = (i as Float + 0.5) / steps as Float * FloatConsts::PI;
+            for j in 0..2 * steps {
+                let phi = (j as Float + 0.5) / steps as Float * FloatConsts::PI;
+                let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                integral += light.pdf(&Vec3::zero(), &direction) * theta.sin();
+            }
+        }
+        integral *= (FloatConsts::PI / steps as Float).powi(2);
+        assert!((integral - 1.0).abs() < 0.02, "integral {}", integral);
+
+        let mut bright = 0;
+        for _ in 0..1000 {
+            let sample = light.sample(&Vec3::zero()).unwrap();
+            let pdf = light.pdf(&Vec3::zero(), &sample.wi);
+            assert!((pdf - sample.pdf).abs() <= 1e-2 * pdf, "{} != {}", pdf, sample.pdf);
+            if sample.radiance.x > 1.0 {
+                bright += 1;
+            }
+        }
+        assert!(bright > 900);
+    }
+}
@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use crate::{hittable::aabb::AABB, math::vec3::Vec3, ray::Ray, utils::{distribution::Distribution1D, random::random}, Float};
+
+use self::tree::LightTree;
+
+pub mod area;
+pub mod punctual;
+pub mod environment;
+pub mod sky;
+pub mod tree;
+
+pub(crate) fn luminance(color: &Vec3) -> Float {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// Light arriving at a shading point from a sampled direction
+pub struct LightSample {
+    // unit direction from the shading point towards the light
+    pub wi: Vec3,
+    // distance to the light along wi, the end of the shadow ray
+    pub distance: Float,
+    pub radiance: Vec3,
+    // surface normal at the sampled point, zero for lights without a surface
+    pub normal: Vec3,
+    // solid angle density of wi, including the probability of picking the light;
+    // for delta lights only the probability of picking the light
+    pub pdf: Float,
+    // whether rays can reach the light by chance; if not, the sample takes no MIS weight
+    pub hittable: bool,
+}
+
+// Where a light subpath starts: a point on the light and a direction leaving it
+pub struct EmissionSample {
+    pub point: Vec3,
+    // surface normal at point, zero for lights without a surface
+    pub normal: Vec3,
+    pub direction: Vec3,
+    // radiance leaving along direction, radiant intensity for a delta position
+    pub radiance: Vec3,
+    // area density of point, 1 for a delta position
+    pub pdf_position: Float,
+    // solid angle density of direction
+    pub pdf_direction: Float,
+    // whether rays can reach the light by chance, as for LightSample
+    pub hittable: bool,
+}
+
+// Anything the integrator can sample directly instead of hitting by chance
+pub trait Emitter {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample>;
+
+    // Solid angle density with which sample() returns direction from origin,
+    // zero for lights rays can't hit
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> Float;
+
+    // Lights at infinity are seen by the rays that escape the scene
+    fn is_infinite(&self) -> bool {
+        false
+    }
+
+    // Radiance an escaping ray sees along direction
+    fn radiance(&self, _direction: &Vec3) -> Vec3 {
+        Vec3::zero()
+    }
+
+    // Where the light sits, None for directional lights and lights at infinity
+    fn bounds(&self) -> Option<AABB> {
+        None
+    }
+
+    // Rough total emitted power, for picking lights by their contribution
+    fn power(&self) -> Float {
+        1.0
+    }
+
+    // Light tracing: starts a light subpath, None for lights without a position
+    fn sample_emission(&self) -> Option<EmissionSample> {
+        None
+    }
+
+    // Densities with which sample_emission leaves point, whose normal is given,
+    // along direction: (pdf_position, pdf_direction)
+    fn pdf_emission(&self, _point: &Vec3, _normal: &Vec3, _direction: &Vec3) -> (Float, Float) {
+        (0.0, 0.0)
+    }
+
+    // Distance along the ray to where it hits the light, for lights with a surface
+    fn intersect(&self, _ray: &Ray) -> Option<Float> {
+        None
+    }
+}
+
+// How light samples and BSDF samples share a path in multiple importance sampling
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MisHeuristic {
+    Balance,
+    #[default]
+    Power,
+}
+
+impl MisHeuristic {
+    // Weight of a sample drawn with density pdf, when the other
+    // strategy could have produced it with density other_pdf
+    pub fn weight(&self, pdf: Float, other_pdf: Float) -> Float {
+        if pdf <= 0.0 {
+            return 0.0;
+        }
+        // as a ratio, so large densities don't overflow when squared
+        let ratio = other_pdf / pdf;
+        match self {
+            MisHeuristic::Balance => 1.0 / (1.0 + ratio),
+            MisHeuristic::Power => 1.0 / (1.0 + ratio * ratio),
+        }
+    }
+}
+
+// All lights of a scene. Lights without a position and the light tree over
+// the positioned ones share the samples evenly; the tree then picks lights
+// by their estimated contribution. Light subpaths start on the positioned
+// lights, picked by power alone.
+pub struct Lights {
+    emitters: Vec<Arc<dyn Emitter>>,
+    unbounded: Vec<usize>,
+    tree: Option<LightTree>,
+    emission: Option<Distribution1D>,
+}
+
+impl Lights {
+    pub fn new(emitters: Vec<Arc<dyn Emitter>>) -> Self {
+        let mut unbounded = Vec::new();
+        let mut bounded = Vec::new();
+        for (index, emitter) in emitters.iter().enumerate() {
+            match emitter.bounds() {
+                Some(bounds) => bounded.push((index, bounds, emitter.power())),
+                None => unbounded.push(index),
+            }
+        }
+        let emission = if bounded.is_empty() {
+            None
+        } else {
+            let mut powers = vec![0.0; emitters.len()];
+            for (index, _, power) in &bounded {
+                powers[*index] = power.max(1e-6);
+            }
+            Some(Distribution1D::new(powers))
+        };
+        Self { emitters, unbounded, tree: LightTree::new(bounded), emission }
+    }
+
+    pub fn emitter(&self, index: usize) -> &Arc<dyn Emitter> {
+        &self.emitters[index]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emitters.is_empty()
+    }
+
+    fn strategies(&self) -> usize {
+        self.unbounded.len() + self.tree.is_some() as usize
+    }
+
+    pub fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        self.sample_light(origin).map(|(_, sample)| sample)
+    }
+
+    // As sample, also telling which emitter the sample came from
+    pub fn sample_light(&self, origin: &Vec3) -> Option<(usize, LightSample)> {
+        let strategies = self.strategies();
+        if strategies == 0 {
+            return None;
+        }
+        let pick = ((random::<Float>() * strategies as Float) as usize).min(strategies - 1);
+        let (index, probability) = match (self.unbounded.get(pick), &self.tree) {
+            (Some(index), _) => (*index, 1.0),
+            (None, Some(tree)) => tree.sample(origin),
+            (None, None) => return None,
+        };
+        let mut sample = self.emitters[index].sample(origin)?;
+        sample.pdf *= probability / strategies as Float;
+        Some((index, sample))
+    }
+
+    // Density of sampling direction from origin; escaped tells whether the
+    // direction leaves the scene or ends on emissive geometry
+    pub fn pdf(&self, origin: &Vec3, direction: &Vec3, escaped: bool) -> Float {
+        let strategies = self.strategies();
+        if strategies == 0 {
+            return 0.0;
+        }
+        let sum = if escaped {
+            self.unbounded.iter()
+                .map(|index| &self.emitters[*index])
+                .filter(|emitter| emitter.is_infinite())
+                .map(|emitter| emitter.pdf(origin, direction))
+                .sum()
+        } else {
+            self.tree.as_ref().map_or(0.0, |tree| {
+                tree.pdf(origin, direction, |index| self.emitters[index].pdf(origin, direction))
+            })
+        };
+        sum / strategies as Float
+    }
+
+    // Density of sample_light picking the emitter at index and sampling direction
+    // from origin, for weighting a hit on that emitter alone; pdf would also count
+    // the lights behind it
+    pub fn light_pdf(&self, index: usize, origin: &Vec3, direction: &Vec3) -> Float {
+        let strategies = self.strategies();
+        if strategies == 0 {
+            return 0.0;
+        }
+        let probability = if self.unbounded.contains(&index) {
+            1.0
+        } else {
+            let mut probability = 0.0;
+            if let Some(tree) = &self.tree {
+                tree.traverse(origin, direction, |light, p| if light == index { probability = p });
+            }
+            probability
+        };
+        probability * self.emitters[index].pdf(origin, direction) / strategies as Float
+    }
+
+    // Starts a light subpath on a positioned light, returning the light
+    // with the probability of having picked it
+    pub fn sample_emission(&self) -> Option<(usize, Float, EmissionSample)> {
+        let (index, probability) = self.emission.as_ref()?.sample_discrete(random());
+        Some((index, probability, self.emitters[index].sample_emission()?))
+    }
+
+    // Probability of sample_emission picking the light
+    pub fn emission_probability(&self, index: usize) -> Float {
+        self.emission.as_ref().map_or(0.0, |emission| emission.discrete_pdf(index))
+    }
+
+    // The positioned light a ray from origin along direction hits at distance
+    pub fn find(&self, origin: &Vec3, direction: &Vec3, distance: Float) -> Option<usize> {
+        let tree = self.tree.as_ref()?;
+        let ray = Ray::new(*origin, *direction);
+        let mut found = None;
+        tree.traverse(origin, direction, |index, _| {
+            if found.is_none() && self.emitters[index].intersect(&ray).is_some_and(|t| (t - distance).abs() <= 1e-3 * distance.max(1.0)) {
+                found = Some(index);
+            }
+        });
+        found
+    }
+
+    // What an escaping ray sees, None when the scene has no light at infinity
+    pub fn background(&self, direction: &Vec3) -> Option<Vec3> {
+        let mut infinite = self.emitters.iter().filter(|emitter| emitter.is_infinite()).peekable();
+        infinite.peek()?;
+        Some(infinite.fold(Vec3::zero(), |sum, emitter| sum + emitter.radiance(direction)))
+    }
+}
+
+unsafe impl Send for Lights {}
+unsafe impl Sync for Lights {}
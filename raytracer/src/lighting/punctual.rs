@@ -0,0 +1,281 @@
+use crate::{hittable::aabb::AABB, math::{onb::Onb, vec3::Vec3}, Float, FloatConsts};
+
+use super::{luminance, EmissionSample, Emitter, LightSample};
+
+// Light from a single point, given as radiant intensity per steradian
+#[derive(Clone, Copy)]
+pub struct PointLight {
+    position: Vec3,
+    intensity: Vec3,
+    radius: Float,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Vec3) -> Self {
+        Self { position, intensity, radius: 0.0 }
+    }
+
+    // Spreads the light over a sphere for soft shadows, keeping the intensity
+    // seen from afar. The sphere itself is never hit by rays.
+    pub fn with_radius(mut self, radius: Float) -> Self {
+        self.radius = radius.max(0.0);
+        self
+    }
+}
+
+impl Emitter for PointLight {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let to_light = self.position - *origin;
+        let distance_squared = to_light.squared_length();
+        if distance_squared <= self.radius * self.radius {
+            return None;
+        }
+        if self.radius == 0.0 {
+            return Some(LightSample {
+                wi: to_light.normalized(),
+                distance: distance_squared.sqrt(),
+                radiance: self.intensity / distance_squared,
+                normal: Vec3::zero(),
+                pdf: 1.0,
+                hittable: false,
+            });
+        }
+
+        // uniform over the cone the sphere subtends, as for sphere area lights
+        let sin2 = self.radius * self.radius / distance_squared;
+        let extent = if sin2 < 1e-4 { 0.5 * sin2 } else { 1.0 - (1.0 - sin2).sqrt() };
+        let wi = Onb::new(to_light).to_world(Vec3::new_random_in_cone(extent)).normalized();
+        // nearest intersection with the sphere along wi
+        let along = to_light.dot(&wi);
+        let distance = along - (self.radius * self.radius - (distance_squared - along * along)).max(0.0).sqrt();
+        Some(LightSample {
+            wi,
+            distance,
+            radiance: self.intensity / (FloatConsts::PI * self.radius * self.radius),
+            normal: (*origin + wi * distance - self.position) / self.radius,
+            pdf: 1.0 / (2.0 * FloatConsts::PI * extent),
+            hittable: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Vec3, _direction: &Vec3) -> Float {
+        0.0
+    }
+
+    fn bounds(&self) -> Option<AABB> {
+        let radius = Vec3::new_diagonal(self.radius);
+        Some(AABB::new(self.position - radius, self.position + radius))
+    }
+
+    fn power(&self) -> Float {
+        4.0 * FloatConsts::PI * luminance(&self.intensity)
+    }
+
+    fn sample_emission(&self) -> Option<EmissionSample> {
+        if self.radius == 0.0 {
+            return Some(EmissionSample {
+                point: self.position,
+                normal: Vec3::zero(),
+                direction: Vec3::new_random_unit_vector(),
+                radiance: self.intensity,
+                pdf_position: 1.0,
+                pdf_direction: 0.25 / FloatConsts::PI,
+                hittable: false,
+            });
+        }
+        let normal = Vec3::new_random_unit_vector();
+        let local = Vec3::new_random_cosine_direction();
+        Some(EmissionSample {
+            point: self.position + self.radius * normal,
+            normal,
+            direction: Onb::new(normal).to_world(local).normalized(),
+            radiance: self.intensity / (FloatConsts::PI * self.radius * self.radius),
+            pdf_position: 0.25 / (FloatConsts::PI * self.radius * self.radius),
+            pdf_direction: local.z / FloatConsts::PI,
+            hittable: false,
+        })
+    }
+
+    fn pdf_emission(&self, _point: &Vec3, normal: &Vec3, direction: &Vec3) -> (Float, Float) {
+        if self.radius == 0.0 {
+            return (1.0, 0.25 / FloatConsts::PI);
+        }
+        (0.25 / (FloatConsts::PI * self.radius * self.radius), normal.dot(direction).max(0.0) / FloatConsts::PI)
+    }
+}
+
+// Point light restricted to a cone, fading out between the inner and outer angle
+#[derive(Clone, Copy)]
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    intensity: Vec3,
+    cos_inner: Float,
+    cos_outer: Float,
+}
+
+impl SpotLight {
+    // Cone half angles in degrees
+    pub fn new(position: Vec3, direction: Vec3, intensity: Vec3, inner_angle: Float, outer_angle: Float) -> Self {
+        let outer_angle = outer_angle.clamp(0.0, 180.0);
+        let inner_angle = inner_angle.clamp(0.0, outer_angle);
+        Self {
+            position,
+            direction: direction.normalized(),
+            intensity,
+            cos_inner: inner_angle.to_radians().cos(),
+            cos_outer: outer_angle.to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos: Float) -> Float {
+        if cos >= self.cos_inner {
+            return 1.0;
+        }
+        if cos <= self.cos_outer {
+            return 0.0;
+        }
+        let t = (cos - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Emitter for SpotLight {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        let to_light = self.position - *origin;
+        let distance_squared = to_light.squared_length();
+        let wi = to_light.normalized();
+        let falloff = self.falloff(-wi.dot(&self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            distance: distance_squared.sqrt(),
+            radiance: self.intensity * falloff / distance_squared,
+            normal: Vec3::zero(),
+            pdf: 1.0,
+            hittable: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Vec3, _direction: &Vec3) -> Float {
+        0.0
+    }
+
+    fn bounds(&self) -> Option<AABB> {
+        Some(AABB::new(self.position, self.position))
+    }
+
+    // the fade counts as half lit
+    fn power(&self) -> Float {
+        2.0 * FloatConsts::PI * (1.0 - 0.5 * (self.cos_inner + self.cos_outer)) * luminance(&self.intensity)
+    }
+
+    // Uniform over the outer cone
+    fn sample_emission(&self) -> Option<EmissionSample> {
+        let extent = 1.0 - self.cos_outer;
+        if extent <= 0.0 {
+            return None;
+        }
+        let direction = Onb::new(self.direction).to_world(Vec3::new_random_in_cone(extent)).normalized();
+        Some(EmissionSample {
+            point: self.position,
+            normal: Vec3::zero(),
+            direction,
+            radiance: self.intensity * self.falloff(direction.dot(&self.direction)),
+            pdf_position: 1.0,
+            pdf_direction: 1.0 / (2.0 * FloatConsts::PI * extent),
+            hittable: false,
+        })
+    }
+
+    fn pdf_emission(&self, _point: &Vec3, _normal: &Vec3, direction: &Vec3) -> (Float, Float) {
+        let extent = 1.0 - self.cos_outer;
+        if extent <= 0.0 || direction.dot(&self.direction) < self.cos_outer {
+            return (1.0, 0.0);
+        }
+        (1.0, 1.0 / (2.0 * FloatConsts::PI * extent))
+    }
+}
+
+// Parallel light from infinitely far away, given as irradiance on a surface facing it
+#[derive(Clone, Copy)]
+pub struct DirectionalLight {
+    direction: Vec3,
+    irradiance: Vec3,
+}
+
+impl DirectionalLight {
+    // direction is the way the light travels
+    pub fn new(direction: Vec3, irradiance: Vec3) -> Self {
+        Self { direction: direction.normalized(), irradiance }
+    }
+}
+
+impl Emitter for DirectionalLight {
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        Some(LightSample {
+            wi: -self.direction,
+            distance: Float::INFINITY,
+            radiance: self.irradiance,
+            normal: Vec3::zero(),
+            pdf: 1.0,
+            hittable: false,
+        })
+    }
+
+    fn pdf(&self, _origin: &Vec3, _direction: &Vec3) -> Float {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_radius() {
+        // a sphere light looks like the point light it replaces from afar
+        let intensity = Vec3::new_diagonal(10.0);
+        let point = PointLight::new(Vec3::new(0.0, 4.0, 0.0), intensity);
+        let sphere = point.with_radius(0.5);
+        let origin = Vec3::zero();
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        let sample = point.sample(&origin).unwrap();
+        let expected = sample.radiance.x * sample.wi.dot(&normal) / sample.pdf;
+        assert!((expected - 10.0 / 16.0).abs() < 1e-5);
+
+        let n = 20000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let sample = sphere.sample(&origin).unwrap();
+            assert!(sample.distance < 4.0 && sample.distance >= 3.5 - 1e-4);
+            sum += sample.radiance.x * sample.wi.dot(&normal) / sample.pdf;
+        }
+        assert!((sum / n as Float - expected).abs() < 0.01 * expected);
+        assert!(sphere.sample(&Vec3::new(0.0, 3.8, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_spot_cone() {
+        let spot = SpotLight::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), Vec3::new_diagonal(1.0), 20.0, 30.0);
+        let below = spot.sample(&Vec3::zero()).unwrap();
+        assert!((below.radiance.x - 1.0).abs() < 1e-5);
+        // 25 degrees off axis is half way through the fade
+        let edge = spot.sample(&Vec3::new(25.0_f32.to_radians().tan(), 0.0, 0.0)).unwrap();
+        let cos2 = 1.0 + 25.0_f32.to_radians().tan().powi(2);
+        assert!(edge.radiance.x * cos2 > 0.3 && edge.radiance.x * cos2 < 0.7);
+        assert!(spot.sample(&Vec3::new(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_directional() {
+        let sun = DirectionalLight::new(Vec3::new(0.0, -2.0, 0.0), Vec3::new_diagonal(3.0));
+        let sample = sun.sample(&Vec3::new(5.0, 1.0, -2.0)).unwrap();
+        assert_eq!(sample.wi, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.radiance, Vec3::new_diagonal(3.0));
+        assert!(sample.distance.is_infinite());
+    }
+}
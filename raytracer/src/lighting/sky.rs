@@ -0,0 +1,283 @@
+use crate::{math::{onb::Onb, vec3::Vec3}, Float, FloatConsts};
+
+use super::{environment::EnvironmentLight, Emitter, LightSample};
+
+// Preetham luminances are in kcd/m^2; scaled so that a white diffuse
+// surface under a clear noon sky renders close to 1
+const LUMINANCE_SCALE: Float = 0.025;
+// Luminance of the sun disk above the atmosphere, in kcd/m^2
+const SUN_LUMINANCE: Float = 1.6e6;
+const SUN_RADIUS_DEGREES: Float = 0.2665;
+// Resolution the sky is tabulated at for importance sampling
+const TABLE_WIDTH: usize = 256;
+const TABLE_HEIGHT: usize = 128;
+
+// Direction towards the sun in scene space (y up, x east, -z north), for a place
+// given in degrees (north and east positive), a day of the year and the UTC time
+// in hours. Uses the NOAA fit for declination and equation of time.
+pub fn sun_direction(latitude: Float, longitude: Float, day_of_year: u32, utc_hours: Float) -> Vec3 {
+    let year = 2.0 * FloatConsts::PI / 365.0 * (day_of_year as Float - 1.0 + (utc_hours - 12.0) / 24.0);
+    let equation_of_time = 229.18 * (0.000075 + 0.001868 * year.cos() - 0.032077 * year.sin()
+        - 0.014615 * (2.0 * year).cos() - 0.040849 * (2.0 * year).sin());
+    let declination = 0.006918 - 0.399912 * year.cos() + 0.070257 * year.sin()
+        - 0.006758 * (2.0 * year).cos() + 0.000907 * (2.0 * year).sin()
+        - 0.002697 * (3.0 * year).cos() + 0.00148 * (3.0 * year).sin();
+    let solar_minutes = utc_hours * 60.0 + equation_of_time + 4.0 * longitude;
+    let hour_angle = (solar_minutes / 4.0 - 180.0).to_radians();
+    let latitude = latitude.to_radians();
+
+    let east = -declination.cos() * hour_angle.sin();
+    let north = latitude.cos() * declination.sin() - latitude.sin() * declination.cos() * hour_angle.cos();
+    let up = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    Vec3::new(east, up, -north).normalized()
+}
+
+// Perez et al. luminance distribution
+#[derive(Clone, Copy)]
+struct Perez {
+    a: Float,
+    b: Float,
+    c: Float,
+    d: Float,
+    e: Float,
+}
+
+impl Perez {
+    fn eval(&self, cos_theta: Float, gamma: Float) -> Float {
+        (1.0 + self.a * (self.b / cos_theta).exp()) * (1.0 + self.c * (self.d * gamma).exp() + self.e * gamma.cos().powi(2))
+    }
+}
+
+fn xyy_to_rgb(x: Float, y: Float, luminance: Float) -> Vec3 {
+    if y <= 0.0 {
+        return Vec3::zero();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Vec3::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+// Analytic clear sky of Preetham, Shirley and Smits, "A Practical Analytic Model
+// for Daylight". The sun direction sets the sky colors and turbidity the haze,
+// from 2 (very clear) to about 10. Below the horizon a diffuse ground of the given
+// albedo reflects the light of sky and sun. Add sun() as well for the sun disk.
+pub struct SkyLight {
+    environment: EnvironmentLight,
+    sun_direction: Vec3,
+    turbidity: Float,
+    intensity: Float,
+}
+
+impl SkyLight {
+    pub fn new(sun_direction: Vec3, turbidity: Float, ground_albedo: Vec3) -> Self {
+        let sun_direction = sun_direction.normalized();
+        let turbidity = turbidity.clamp(1.7, 10.0);
+        let sky = Self::preetham(&sun_direction, turbidity);
+
+        // irradiance on the ground, for the light it reflects back up
+        let steps = 64;
+        let mut irradiance = Vec3::zero();
+        for i in 0..steps {
+            let theta = (i as Float + 0.5) / steps as Float * 0.5 * FloatConsts::PI;
+            for j in 0..2 * steps {
+                let phi = (j as Float + 0.5) / (2 * steps) as Float * 2.0 * FloatConsts::PI;
+                let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                irradiance += sky(&direction) * theta.cos() * theta.sin();
+            }
+        }
+        irradiance *= (0.5 * FloatConsts::PI / steps as Float) * (FloatConsts::PI / steps as Float);
+        let sun = SunLight::new(sun_direction, turbidity);
+        irradiance += sun.radiance * 2.0 * FloatConsts::PI * sun.extent * sun_direction.y.max(0.0);
+        let ground = ground_albedo * irradiance / FloatConsts::PI;
+
+        let environment = EnvironmentLight::from_fn(TABLE_WIDTH, TABLE_HEIGHT, |direction| {
+            if direction.y >= 0.0 { sky(direction) } else { ground }
+        });
+        Self { environment, sun_direction, turbidity, intensity: 1.0 }
+    }
+
+    pub fn with_intensity(mut self, intensity: Float) -> Self {
+        self.environment = self.environment.with_intensity(intensity);
+        self.intensity = intensity;
+        self
+    }
+
+    // Sun disk matching this sky
+    pub fn sun(&self) -> SunLight {
+        SunLight::new(self.sun_direction, self.turbidity).with_intensity(self.intensity)
+    }
+
+    fn preetham(sun_direction: &Vec3, turbidity: Float) -> impl Fn(&Vec3) -> Vec3 {
+        let t = turbidity;
+        // the model only covers a sun above the horizon
+        let sun = Vec3::new(sun_direction.x, sun_direction.y.max(0.0), sun_direction.z).normalized();
+        let theta_s = sun.y.clamp(0.0, 1.0).acos();
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (FloatConsts::PI - 2.0 * theta_s);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let chromaticity = |m: [[Float; 4]; 3]| {
+            let angles = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+            let row = |r: [Float; 4]| r.iter().zip(angles).map(|(a, b)| a * b).sum::<Float>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let perez_luminance = Perez { a: 0.1787 * t - 1.4630, b: -0.3554 * t + 0.4275, c: -0.0227 * t + 5.3251, d: 0.1206 * t - 2.5771, e: -0.0670 * t + 0.3703 };
+        let perez_x = Perez { a: -0.0193 * t - 0.2592, b: -0.0665 * t + 0.0008, c: -0.0004 * t + 0.2125, d: -0.0641 * t - 0.8989, e: -0.0033 * t + 0.0452 };
+        let perez_y = Perez { a: -0.0167 * t - 0.2608, b: -0.0950 * t + 0.0092, c: -0.0079 * t + 0.2102, d: -0.0441 * t - 1.6537, e: -0.0109 * t + 0.0529 };
+
+        move |direction: &Vec3| {
+            let cos_theta = direction.y.max(0.01);
+            let gamma = direction.dot(&sun).clamp(-1.0, 1.0).acos();
+            let relative = |perez: &Perez| perez.eval(cos_theta, gamma) / perez.eval(1.0, theta_s);
+            let luminance = zenith_luminance * relative(&perez_luminance);
+            let x = zenith_x * relative(&perez_x);
+            let y = zenith_y * relative(&perez_y);
+            xyy_to_rgb(x, y, luminance) * LUMINANCE_SCALE
+        }
+    }
+}
+
+impl Emitter for SkyLight {
+    fn sample(&self, origin: &Vec3) -> Option<LightSample> {
+        self.environment.sample(origin)
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3) -> Float {
+        self.environment.pdf(origin, direction)
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        self.environment.radiance(direction)
+    }
+}
+
+// The sun as a small disk at infinity, reddened by the air it shines through
+pub struct SunLight {
+    direction: Vec3,
+    radiance: Vec3,
+    // 1 - cos of the disk's angular radius
+    extent: Float,
+}
+
+impl SunLight {
+    pub fn new(direction: Vec3, turbidity: Float) -> Self {
+        let direction = direction.normalized();
+        let half_angle = (0.5 * SUN_RADIUS_DEGREES).to_radians();
+        let extent = 2.0 * half_angle.sin() * half_angle.sin();
+        let radiance = if direction.y > 0.0 {
+            Self::transmittance(direction.y, turbidity) * SUN_LUMINANCE * LUMINANCE_SCALE
+        } else {
+            Vec3::zero()
+        };
+        Self { direction, radiance, extent }
+    }
+
+    pub fn with_intensity(mut self, intensity: Float) -> Self {
+        self.radiance *= intensity;
+        self
+    }
+
+    // Rayleigh and aerosol extinction along the path through the atmosphere,
+    // at representative red, green and blue wavelengths (Preetham appendix)
+    fn transmittance(cos_theta: Float, turbidity: Float) -> Vec3 {
+        let zenith_degrees = cos_theta.clamp(0.0, 1.0).acos().to_degrees();
+        let air_mass = 1.0 / (cos_theta + 0.15 * (93.885 - zenith_degrees).max(1e-3).powf(-1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+        let channel = |micrometers: Float| {
+            let rayleigh = 0.008735 * micrometers.powf(-4.08);
+            let aerosol = beta * micrometers.powf(-1.3);
+            (-(rayleigh + aerosol) * air_mass).exp()
+        };
+        Vec3::new(channel(0.65), channel(0.57), channel(0.475))
+    }
+
+    fn pdf_value(&self) -> Float {
+        1.0 / (2.0 * FloatConsts::PI * self.extent)
+    }
+
+    fn covers(&self, direction: &Vec3) -> bool {
+        1.0 - direction.normalized().dot(&self.direction) <= self.extent
+    }
+}
+
+impl Emitter for SunLight {
+    fn sample(&self, _origin: &Vec3) -> Option<LightSample> {
+        if self.radiance.near_zero() {
+            return None;
+        }
+        let wi = Onb::new(self.direction).to_world(Vec3::new_random_in_cone(self.extent)).normalized();
+        Some(LightSample { wi, distance: Float::INFINITY, radiance: self.radiance, normal: Vec3::zero(), pdf: self.pdf_value(), hittable: true })
+    }
+
+    fn pdf(&self, _origin: &Vec3, direction: &Vec3) -> Float {
+        if self.covers(direction) { self.pdf_value() } else { 0.0 }
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        if self.covers(direction) { self.radiance } else { Vec3::zero() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_direction() {
+        // equinox at the equator: overhead at noon, rising in the east
+        let noon = sun_direction(0.0, 0.0, 80, 12.0);
+        assert!(noon.y > 0.99, "{}", noon);
+        let sunrise = sun_direction(0.0, 0.0, 80, 6.1);
+        assert!(sunrise.y.abs() < 0.05 && sunrise.x > 0.99, "{}", sunrise);
+        // winter in Oslo: low in the south
+        let winter = sun_direction(59.9, 10.75, 355, 11.3);
+        assert!(winter.y > 0.05 && winter.y < 0.15 && winter.z > 0.95, "{}", winter);
+    }
+
+    #[test]
+    fn test_daylight() {
+        let sun_direction = Vec3::new(0.0, 1.0, 1.0);
+        let sky = SkyLight::new(sun_direction, 3.0, Vec3::new_diagonal(0.3));
+        let zenith = sky.radiance(&Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.z > zenith.x, "zenith {}", zenith);
+        let ground = sky.radiance(&Vec3::new(0.3, -1.0, 0.0));
+        assert!(ground.y > 0.0 && ground.y < zenith.y * 10.0);
+
+        // a white surface under a clear sky lands near 1
+        let sun = sky.sun();
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let n = 20000;
+        let mut irradiance = Vec3::zero();
+        for _ in 0..n {
+            for light in [&sky as &dyn Emitter, &sun] {
+                let sample = light.sample(&Vec3::zero()).unwrap();
+                assert!(light.radiance(&sample.wi).length() > 0.0);
+                irradiance += sample.radiance * sample.wi.dot(&normal).max(0.0) / sample.pdf;
+            }
+        }
+        let white = irradiance / n as Float / FloatConsts::PI;
+        assert!(white.y > 0.3 && white.y < 1.5, "white {}", white);
+        assert_eq!(sun.radiance(&-sun_direction), Vec3::zero());
+    }
+}
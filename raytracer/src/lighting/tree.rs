@@ -0,0 +1,170 @@
+use crate::{hittable::aabb::AABB, math::vec3::Vec3, ray::Ray, utils::random::random, Float};
+
+enum Node {
+    Leaf { bounds: AABB, power: Float, light: usize },
+    Interior { bounds: AABB, power: Float, left: usize, right: usize },
+}
+
+impl Node {
+    fn bounds(&self) -> &AABB {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Interior { bounds, .. } => bounds,
+        }
+    }
+
+    fn power(&self) -> Float {
+        match self {
+            Node::Leaf { power, .. } | Node::Interior { power, .. } => *power,
+        }
+    }
+
+    // Estimated contribution of everything below this node at point:
+    // power over squared distance, never closer than the node's own extent
+    fn importance(&self, point: &Vec3) -> Float {
+        let bounds = self.bounds();
+        let center = 0.5 * (bounds.min() + bounds.max());
+        let half_diagonal = 0.5 * (bounds.max() - bounds.min());
+        self.power() / (center - *point).squared_length().max(half_diagonal.squared_length()).max(1e-8)
+    }
+}
+
+// Bounding volume hierarchy over the lights that have a position. Walking down
+// from the root picks a light with a probability following its estimated
+// contribution, in time logarithmic in the number of lights.
+pub struct LightTree {
+    nodes: Vec<Node>,
+    root: usize,
+}
+
+impl LightTree {
+    // Takes (light index, bounds, power) triples, None when there are none
+    pub fn new(mut lights: Vec<(usize, AABB, Float)>) -> Option<Self> {
+        if lights.is_empty() {
+            return None;
+        }
+        let mut tree = Self { nodes: Vec::with_capacity(2 * lights.len()), root: 0 };
+        tree.root = tree.build(&mut lights);
+        Some(tree)
+    }
+
+    fn build(&mut self, lights: &mut [(usize, AABB, Float)]) -> usize {
+        if let [(light, bounds, power)] = lights {
+            // a light estimated dark still gets picked now and then
+            self.nodes.push(Node::Leaf { bounds: *bounds, power: power.max(1e-6), light: *light });
+            return self.nodes.len() - 1;
+        }
+        let centroid = |bounds: &AABB| 0.5 * (bounds.min() + bounds.max());
+        let centroids = lights.iter().fold(AABB::new(centroid(&lights[0].1), centroid(&lights[0].1)), |merged, (_, bounds, _)| {
+            AABB::merge(merged, AABB::new(centroid(bounds), centroid(bounds)))
+        });
+        let axis = centroids.longest_axis();
+        lights.sort_by(|a, b| centroid(&a.1)[axis].total_cmp(&centroid(&b.1)[axis]));
+        let (left_lights, right_lights) = lights.split_at_mut(lights.len() / 2);
+        let left = self.build(left_lights);
+        let right = self.build(right_lights);
+        let bounds = AABB::merge(*self.nodes[left].bounds(), *self.nodes[right].bounds());
+        let power = self.nodes[left].power() + self.nodes[right].power();
+        self.nodes.push(Node::Interior { bounds, power, left, right });
+        self.nodes.len() - 1
+    }
+
+    // Probability of going left at an interior node, seen from point
+    fn left_probability(&self, left: usize, right: usize, point: &Vec3) -> Float {
+        let left = self.nodes[left].importance(point);
+        let right = self.nodes[right].importance(point);
+        if left + right <= 0.0 {
+            return 0.5;
+        }
+        left / (left + right)
+    }
+
+    // Picks a light for a shading point, returning it with its probability
+    pub fn sample(&self, point: &Vec3) -> (usize, Float) {
+        let mut node = self.root;
+        let mut probability = 1.0;
+        loop {
+            match &self.nodes[node] {
+                Node::Leaf { light, .. } => return (*light, probability),
+                Node::Interior { left, right, .. } => {
+                    let p = self.left_probability(*left, *right, point);
+                    if random::<Float>() < p {
+                        node = *left;
+                        probability *= p;
+                    } else {
+                        node = *right;
+                        probability *= 1.0 - p;
+                    }
+                }
+            }
+        }
+    }
+
+    // Calls visit(light, probability of picking it) for the lights whose
+    // bounds the ray from point along direction passes through
+    pub fn traverse(&self, point: &Vec3, direction: &Vec3, mut visit: impl FnMut(usize, Float)) {
+        let ray = Ray::new(*point, *direction);
+        let mut stack = vec![(self.root, 1.0)];
+        while let Some((node, probability)) = stack.pop() {
+            if !self.nodes[node].bounds().intersect(&ray, 0.001..Float::INFINITY) {
+                continue;
+            }
+            match &self.nodes[node] {
+                Node::Leaf { light, .. } => visit(*light, probability),
+                Node::Interior { left, right, .. } => {
+                    let p = self.left_probability(*left, *right, point);
+                    stack.push((*left, probability * p));
+                    stack.push((*right, probability * (1.0 - p)));
+                }
+            }
+        }
+    }
+
+    // Sums the probability of picking each light times light_pdf(light), over the
+    // lights whose bounds the ray from point along direction passes through
+    pub fn pdf(&self, point: &Vec3, direction: &Vec3, light_pdf: impl Fn(usize) -> Float) -> Float {
+        let mut sum = 0.0;
+        self.traverse(point, direction, |light, probability| sum += probability * light_pdf(light));
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{hittable::{quad::Quad, Hittable}, lighting::{area::AreaLight, Emitter, Lights}, material::{light::Light, Material}};
+
+    use super::*;
+
+    #[test]
+    fn test_many_lights() {
+        // a row of 1024 lamps, looked at from just above the first one
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Light::new(Vec3::new_diagonal(1.0))));
+        let emitters: Vec<Arc<dyn Emitter>> = (0..1024).map(|i| {
+            let quad: Arc<Box<dyn Hittable>> = Arc::new(Box::new(Quad::new(
+                Vec3::new(i as Float, 0.0, 0.0),
+                Vec3::new(0.5, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 0.5),
+                material.clone(),
+            )));
+            Arc::new(AreaLight::new(quad)) as Arc<dyn Emitter>
+        }).collect();
+        let lights = Lights::new(emitters);
+
+        // uniform picking would find the lamp below once in a thousand samples
+        let point = Vec3::new(0.25, 1.0, 0.25);
+        let mut below = 0;
+        for _ in 0..1000 {
+            let (index, sample) = lights.sample_light(&point).unwrap();
+            // the density a light sample reports is the one MIS looks up for its direction
+            let pdf = lights.pdf(&point, &sample.wi, false);
+            assert!((pdf - sample.pdf).abs() <= 1e-3 * pdf, "{} != {}", pdf, sample.pdf);
+            let pdf = lights.light_pdf(index, &point, &sample.wi);
+            assert!((pdf - sample.pdf).abs() <= 1e-3 * pdf, "{} != {}", pdf, sample.pdf);
+            if sample.wi.y < -0.9 {
+                below += 1;
+            }
+        }
+        assert!(below > 150, "{} samples of the lamp below", below);
+    }
+}
@@ -0,0 +1,78 @@
+use std::ops::{BitAnd, BitOr};
+
+use crate::{math::vec3::Vec3, Float};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Lobe(u8);
+
+impl Lobe {
+    pub const NONE: Lobe = Lobe(0);
+    pub const REFLECTION: Lobe = Lobe(1 << 0);
+    pub const TRANSMISSION: Lobe = Lobe(1 << 1);
+    pub const DIFFUSE: Lobe = Lobe(1 << 2);
+    pub const GLOSSY: Lobe = Lobe(1 << 3);
+    // Delta distributions: can't be evaluated or sampled from a given direction
+    pub const SPECULAR: Lobe = Lobe(1 << 4);
+
+    pub fn contains(&self, other: Lobe) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(&self, other: Lobe) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_specular(&self) -> bool {
+        self.intersects(Lobe::SPECULAR)
+    }
+
+    // True when at least one lobe can be evaluated for an arbitrary direction
+    pub fn has_non_specular(&self) -> bool {
+        self.intersects(Lobe::DIFFUSE | Lobe::GLOSSY)
+    }
+}
+
+impl BitOr for Lobe {
+    type Output = Lobe;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Lobe(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Lobe {
+    type Output = Lobe;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Lobe(self.0 & rhs.0)
+    }
+}
+
+pub struct BsdfSample {
+    // sampled incident direction, pointing away from the surface
+    pub wi: Vec3,
+    // f(wo, wi) * |cos(theta_i)| / pdf
+    pub weight: Vec3,
+    // solid angle density of wi; for specular lobes only the discrete
+    // probability of having picked that lobe
+    pub pdf: Float,
+    pub lobe: Lobe,
+    // set when the sample made the rest of the path carry a single wavelength
+    pub wavelength: Option<Float>,
+    // relative IOR of a refraction whose radiance was divided by its square, 1 otherwise
+    pub eta: Float,
+}
+
+impl BsdfSample {
+    pub fn new(wi: Vec3, weight: Vec3, pdf: Float, lobe: Lobe) -> Self {
+        Self { wi, weight, pdf, lobe, wavelength: None, eta: 1.0 }
+    }
+
+    pub fn with_wavelength(mut self, wavelength: Option<Float>) -> Self {
+        self.wavelength = wavelength;
+        self
+    }
+
+    pub fn with_eta(mut self, eta: Float) -> Self {
+        self.eta = eta;
+        self
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, same_hemisphere, Material};
+use crate::{hittable::HitRecord, math::{onb::Onb, vec3::Vec3}, texture::{solid::SolidColor, Texture}, utils::random::random, Float, FloatConsts};
+
+const SHEEN_ALBEDO_SIZE: usize = 32;
+
+// Fabric and velvet: a diffuse base plus a retro/grazing sheen from fibers, using the
+// "Charlie" sheen distribution (Estevez and Kulla 2017) with Neubelt's visibility term.
+#[derive(Clone)]
+pub struct Cloth {
+    albedo: Arc<Box<dyn Texture>>,
+    sheen: Arc<Box<dyn Texture>>,
+    roughness: Float,
+    // directional albedo of the sheen lobe at evenly spaced cos(theta_o) from 0 to 1
+    sheen_albedo: Vec<Float>,
+}
+
+impl Cloth {
+    pub fn new(albedo: Vec3, sheen: Vec3, roughness: Float) -> Cloth {
+        Cloth::new_with_texture(
+            Arc::new(Box::new(SolidColor::new(albedo))),
+            Arc::new(Box::new(SolidColor::new(sheen))),
+            roughness,
+        )
+    }
+
+    pub fn new_with_texture(albedo: Arc<Box<dyn Texture>>, sheen: Arc<Box<dyn Texture>>, roughness: Float) -> Cloth {
+        let mut cloth = Cloth { albedo, sheen, roughness: roughness.clamp(0.07, 1.0), sheen_albedo: Vec::new() };
+        cloth.sheen_albedo = (0..SHEEN_ALBEDO_SIZE)
+            .map(|i| cloth.integrate_sheen(i as Float / (SHEEN_ALBEDO_SIZE - 1) as Float))
+            .collect();
+        cloth
+    }
+
+    // Hemispherical integral of the sheen term times cos(theta_i), by the midpoint rule
+    fn integrate_sheen(&self, cos: Float) -> Float {
+        let wo = Vec3::new((1.0 - cos * cos).max(0.0).sqrt(), 0.0, cos);
+        let (steps_z, steps_phi) = (32, 64);
+        let mut sum = 0.0;
+        for i in 0..steps_z {
+            let z = (i as Float + 0.5) / steps_z as Float;
+            let r = (1.0 - z * z).sqrt();
+            for j in 0..steps_phi {
+                let phi = 2.0 * FloatConsts::PI * (j as Float + 0.5) / steps_phi as Float;
+                sum += self.sheen_term(&wo, &Vec3::new(r * phi.cos(), r * phi.sin(), z)) * z;
+            }
+        }
+        sum * 2.0 * FloatConsts::PI / (steps_z * steps_phi) as Float
+    }
+
+    fn sheen_albedo(&self, cos: Float) -> Float {
+        let x = cos.clamp(0.0, 1.0) * (SHEEN_ALBEDO_SIZE - 1) as Float;
+        let i = (x as usize).min(SHEEN_ALBEDO_SIZE - 2);
+        let t = x - i as Float;
+        self.sheen_albedo[i] * (1.0 - t) + self.sheen_albedo[i + 1] * t
+    }
+
+    fn sheen_term(&self, wo: &Vec3, wi: &Vec3) -> Float {
+        let wh = (*wo + *wi).normalized();
+        let inv_alpha = 1.0 / self.roughness;
+        let sin2 = (1.0 - wh.z * wh.z).max(0.0);
+        let d = (2.0 + inv_alpha) * sin2.powf(0.5 * inv_alpha) / (2.0 * FloatConsts::PI);
+        let v = 1.0 / (4.0 * (wi.z + wo.z - wi.z * wo.z));
+        d * v
+    }
+
+    // The sheen lobe spreads towards grazing angles where cosine sampling rarely goes,
+    // so it is sampled uniformly over the hemisphere instead
+    fn sheen_probability(&self, rec: &HitRecord) -> Float {
+        let albedo = self.albedo.value(rec.u, rec.v, &rec.point);
+        let sheen = self.sheen.value(rec.u, rec.v, &rec.point);
+        let albedo = albedo.x.max(albedo.y).max(albedo.z);
+        let sheen = sheen.x.max(sheen.y).max(sheen.z);
+        if albedo + sheen <= 0.0 {
+            return 0.5;
+        }
+        (sheen / (albedo + sheen)).clamp(0.1, 0.9)
+    }
+}
+
+impl Material for Cloth {
+    fn lobes(&self) -> Lobe {
+        Lobe::DIFFUSE | Lobe::REFLECTION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if !same_hemisphere(&rec.normal, wo, wi) {
+            return Vec3::zero();
+        }
+        let onb = Onb::new(rec.normal);
+        let (wo, wi) = (onb.to_local(*wo), onb.to_local(*wi));
+        let (wo, wi) = (Vec3::new(wo.x, wo.y, wo.z.abs()), Vec3::new(wi.x, wi.y, wi.z.abs()));
+        // what the sheen reflects towards wo doesn't reach the base
+        let sheen = self.sheen.value(rec.u, rec.v, &rec.point);
+        let base = Vec3::new_diagonal(1.0) - sheen * self.sheen_albedo(wo.z);
+        self.albedo.value(rec.u, rec.v, &rec.point) * base / FloatConsts::PI + sheen * self.sheen_term(&wo, &wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let onb = Onb::new(rec.normal);
+        let local = if random::<Float>() < self.sheen_probability(rec) {
+            let z: Float = random();
+            let r = (1.0 - z * z).max(0.0).sqrt();
+            let phi = 2.0 * FloatConsts::PI * random::<Float>();
+            Vec3::new(r * phi.cos(), r * phi.sin(), z)
+        } else {
+            Vec3::new_random_cosine_direction()
+        };
+        if local.z <= 0.0 {
+            return None;
+        }
+        let wi = onb.to_world(local);
+        let pdf = self.pdf(rec, wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let f = self.eval(rec, wo, &wi);
+        Some(BsdfSample::new(wi, f * local.z / pdf, pdf, Lobe::DIFFUSE | Lobe::REFLECTION))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        if !same_hemisphere(&rec.normal, wo, wi) {
+            return 0.0;
+        }
+        let p = self.sheen_probability(rec);
+        p / (2.0 * FloatConsts::PI) + (1.0 - p) * rec.normal.dot(wi).abs() / FloatConsts::PI
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Cloth { albedo: self.albedo.describe()?, sheen: self.sheen.describe()?, roughness: self.roughness })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::tests::assert_sample_consistent, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_consistency() {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            Cloth::new(Vec3::new(0.3, 0.05, 0.1), Vec3::new(0.9, 0.6, 0.7), 0.4)
+        ));
+        let ray = Ray::new(Vec3::new(0.3, 1.0, 0.2), Vec3::new(-0.3, -1.0, -0.2));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        let wo = -ray.direction();
+        assert_sample_consistent(&material, &rec, &wo, 1e-4);
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // white fabric under a full white sheen reflects everything that comes in, no more
+        for roughness in [0.1, 0.4, 1.0] {
+            for wo in [Vec3::new(0.3, 1.0, 0.2), Vec3::new(2.0, 0.3, 0.0)] {
+                let material: Arc<Box<dyn Material>> = Arc::new(Box::new(
+                    Cloth::new(Vec3::new_diagonal(1.0), Vec3::new_diagonal(1.0), roughness)
+                ));
+                let wo = wo.normalized();
+                let ray = Ray::new(wo, -wo);
+                let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+                let samples: Vec<BsdfSample> = (0..20)
+                    .flat_map(|_| assert_sample_consistent(&material, &rec, &wo, 1e-4))
+                    .collect();
+                let albedo = samples.iter().map(|sample| sample.weight.x).sum::<Float>() / samples.len() as Float;
+                assert!((albedo - 1.0).abs() < 0.03, "albedo {} for roughness {} at {:?}", albedo, roughness, wo);
+            }
+        }
+    }
+}
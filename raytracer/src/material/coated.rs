@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use crate::{hittable::HitRecord, math::vec3::Vec3, utils::{random::random, spectrum}, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, fresnel, library::{to_array, FilmDesc, MaterialDesc}, medium::Medium, microfacet::{reflect, GGX}, Material};
+
+// A dielectric coat (smooth or GGX rough) layered over any base material.
+// Light the coat doesn't reflect enters the layer, may be absorbed on its way
+// down and back up, and leaves through the coat again, so the base is weighted by
+// (1 - F(wo)) * (1 - F(wi)). Internal reflections are ignored, which can only
+// lose energy, never create it.
+#[derive(Clone)]
+pub struct Coated {
+    base: Arc<Box<dyn Material>>,
+    refraction_index: Float,
+    distribution: GGX,
+    tint: Vec3,
+    // absorption coefficient times layer thickness
+    absorption: Vec3,
+    // IOR and thickness (nm) of an interference film on top of the coat
+    film: Option<(Float, Float)>,
+}
+
+impl Coated {
+    pub fn new(base: Arc<Box<dyn Material>>, refraction_index: Float, roughness: Float) -> Self {
+        Self {
+            base,
+            refraction_index,
+            distribution: GGX::from_roughness(roughness, roughness),
+            tint: Vec3::new_diagonal(1.0),
+            absorption: Vec3::zero(),
+            film: None,
+        }
+    }
+
+    // Tint of the coat: light crossing it straight down and up keeps `color`
+    pub fn with_tint(mut self, color: Vec3) -> Self {
+        self.tint = color;
+        self.absorption = Vec3::new(
+            -0.5 * color.x.max(1e-6).ln(),
+            -0.5 * color.y.max(1e-6).ln(),
+            -0.5 * color.z.max(1e-6).ln(),
+        );
+        self
+    }
+
+    // Iridescent coat, like oil on water or tempered steel
+    pub fn with_thin_film(mut self, film_ior: Float, thickness: Float) -> Self {
+        self.film = Some((film_ior, thickness));
+        self
+    }
+
+    fn fresnel(&self, rec: &HitRecord, cos: Float) -> Vec3 {
+        let Some((film_ior, thickness)) = self.film else {
+            return Vec3::new_diagonal(fresnel::dielectric(cos.abs(), self.refraction_index));
+        };
+        let reflectance = |wavelength| fresnel::thin_film(cos, film_ior, thickness, self.refraction_index, wavelength);
+        match rec.wavelength {
+            Some(wavelength) => Vec3::new_diagonal(reflectance(wavelength)),
+            None => spectrum::integrate_rgb(reflectance),
+        }
+    }
+
+    // Transmittance of the layer for a path going down along wo and up along wi
+    fn layer_transmittance(&self, cos_o: Float, cos_i: Float) -> Vec3 {
+        let refracted_cos = |cos: Float| {
+            let sin2 = (1.0 - cos * cos) / (self.refraction_index * self.refraction_index);
+            (1.0 - sin2).max(1e-4).sqrt()
+        };
+        let path = 1.0 / refracted_cos(cos_o) + 1.0 / refracted_cos(cos_i);
+        let tau = self.absorption * path;
+        Vec3::new((-tau.x).exp(), (-tau.y).exp(), (-tau.z).exp())
+    }
+
+    // Share of the light leaving the base that gets out along a direction at cos
+    // to the normal: one way up through the layer, then through the coat
+    fn exit_transmittance(&self, rec: &HitRecord, cos: Float) -> Vec3 {
+        let round_trip = self.layer_transmittance(cos, cos);
+        let one_way = Vec3::new(round_trip.x.sqrt(), round_trip.y.sqrt(), round_trip.z.sqrt());
+        (Vec3::new_diagonal(1.0) - self.fresnel(rec, cos)) * one_way
+    }
+
+    // Probability of sampling the coat instead of the base
+    fn coat_probability(&self, rec: &HitRecord, cos_o: Float) -> Float {
+        let f = self.fresnel(rec, cos_o);
+        ((f.x + f.y + f.z) / 3.0).clamp(0.05, 0.95)
+    }
+
+    fn eval_coat(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> (Vec3, Float) {
+        if self.distribution.effectively_smooth() {
+            return (Vec3::zero(), 0.0);
+        }
+        let frame = rec.shading_frame();
+        let (wo, wi) = (frame.to_local(*wo), frame.to_local(*wi));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return (Vec3::zero(), 0.0);
+        }
+        let wm = (wo + wi).normalized();
+        let f = self.distribution.d(&wm) * self.distribution.g(&wo, &wi) / (4.0 * wo.z * wi.z)
+            * self.fresnel(rec, wo.dot(&wm));
+        let pdf = self.distribution.d_visible(&wo, &wm) / (4.0 * wo.dot(&wm).abs());
+        (f, pdf)
+    }
+
+    fn base_weight(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let cos_o = rec.normal.dot(wo).abs();
+        let cos_i = rec.normal.dot(wi).abs();
+        let one = Vec3::new_diagonal(1.0);
+        (one - self.fresnel(rec, cos_o)) * (one - self.fresnel(rec, cos_i)) * self.layer_transmittance(cos_o, cos_i)
+    }
+}
+
+impl Material for Coated {
+    fn lobes(&self) -> Lobe {
+        let coat = if self.distribution.effectively_smooth() {
+            Lobe::SPECULAR | Lobe::REFLECTION
+        } else {
+            Lobe::GLOSSY | Lobe::REFLECTION
+        };
+        coat | self.base.lobes()
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let (coat, _) = self.eval_coat(rec, wo, wi);
+        coat + self.base_weight(rec, wo, wi) * self.base.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let cos_o = rec.normal.dot(wo);
+        if cos_o <= 0.0 {
+            return None;
+        }
+        let coat_probability = self.coat_probability(rec, cos_o);
+
+        if random::<Float>() < coat_probability {
+            let frame = rec.shading_frame();
+            let wo_local = frame.to_local(*wo);
+            if self.distribution.effectively_smooth() {
+                let wi = Vec3::new(-wo_local.x, -wo_local.y, wo_local.z);
+                return Some(BsdfSample::new(
+                    frame.to_world(wi),
+                    self.fresnel(rec, cos_o) / coat_probability,
+                    coat_probability,
+                    Lobe::SPECULAR | Lobe::REFLECTION,
+                ));
+            }
+            let wm = self.distribution.sample_wm(&wo_local);
+            let wi_local = reflect(&wo_local, &wm);
+            if wi_local.z <= 0.0 {
+                return None;
+            }
+            return self.combined_sample(rec, wo, frame.to_world(wi_local), Lobe::GLOSSY | Lobe::REFLECTION, 1.0);
+        }
+
+        let sample = self.base.sample(rec, wo)?;
+        if sample.lobe.is_specular() {
+            return Some(BsdfSample::new(
+                sample.wi,
+                sample.weight * self.base_weight(rec, wo, &sample.wi) / (1.0 - coat_probability),
+                sample.pdf * (1.0 - coat_probability),
+                sample.lobe,
+            ).with_wavelength(sample.wavelength).with_eta(sample.eta));
+        }
+        self.combined_sample(rec, wo, sample.wi, sample.lobe, sample.eta)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        let cos_o = rec.normal.dot(wo);
+        if cos_o <= 0.0 {
+            return 0.0;
+        }
+        let coat_probability = self.coat_probability(rec, cos_o);
+        let (_, coat_pdf) = self.eval_coat(rec, wo, wi);
+        coat_probability * coat_pdf + (1.0 - coat_probability) * self.base.pdf(rec, wo, wi)
+    }
+
+    fn emitted(&self, rec: &HitRecord, wo: &Vec3) -> Option<Vec3> {
+        let emission = self.base.emitted(rec, wo)?;
+        Some(emission * self.exit_transmittance(rec, rec.normal.dot(wo).abs()))
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.base.is_emissive()
+    }
+
+    fn interior(&self) -> Option<&Medium> {
+        self.base.interior()
+    }
+
+    fn is_opaque(&self, u: Float, v: Float, point: &Vec3) -> bool {
+        self.base.is_opaque(u, v, point)
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        let (roughness, _) = self.distribution.roughness();
+        Some(MaterialDesc::Coated {
+            base: Box::new(self.base.describe()?),
+            refraction_index: self.refraction_index,
+            roughness,
+            tint: to_array(self.tint),
+            film: self.film.map(|(ior, thickness)| FilmDesc { ior, thickness }),
+        })
+    }
+}
+
+impl Coated {
+    fn combined_sample(&self, rec: &HitRecord, wo: &Vec3, wi: Vec3, lobe: Lobe, eta: Float) -> Option<BsdfSample> {
+        let pdf = self.pdf(rec, wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let f = self.eval(rec, wo, &wi);
+        Some(BsdfSample::new(wi, f * rec.normal.dot(&wi).abs() / pdf, pdf, lobe).with_eta(eta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::{conductor::{Conductor, ConductorPreset}, lambertian::Lambertian, light::Light, roughdielectric::RoughDielectric, subsurface::Subsurface, tests::assert_sample_consistent}, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_energy_conservation() {
+        let base: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::new_diagonal(1.0))));
+        for roughness in [0.0, 0.3] {
+            let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Coated::new(base.clone(), 1.5, roughness)));
+            let ray = Ray::new(Vec3::new(0.5, 1.0, 0.2), Vec3::new(-0.5, -1.0, -0.2));
+            let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+            let wo = -ray.direction();
+            let n = 20000;
+            let mut albedo = Vec3::zero();
+            for _ in 0..n {
+                if let Some(sample) = material.sample(&rec, &wo) {
+                    albedo += sample.weight;
+                }
+            }
+            albedo /= n as Float;
+            assert!(albedo.x < 1.02, "albedo {} for roughness {}", albedo, roughness);
+            assert!(albedo.x > 0.7, "albedo {} for roughness {}", albedo, roughness);
+        }
+    }
+
+    #[test]
+    fn test_sample_consistency() {
+        let base: Arc<Box<dyn Material>> = Arc::new(Box::new(Conductor::new_preset(ConductorPreset::Aluminium, 0.5)));
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            Coated::new(base, 1.5, 0.1).with_tint(Vec3::new(0.8, 0.1, 0.1))
+        ));
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 0.2), Vec3::new(-0.5, -1.0, -0.2));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        let wo = -ray.direction();
+        assert_sample_consistent(&material, &rec, &wo, 1e-3);
+    }
+
+    #[test]
+    fn test_passes_through_base() {
+        // emission leaves through the coat, which reflects F back and the tint absorbs
+        let light: Arc<Box<dyn Material>> = Arc::new(Box::new(Light::new(Vec3::new_diagonal(4.0))));
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            Coated::new(light, 1.5, 0.0).with_tint(Vec3::new(1.0, 0.25, 1.0))
+        ));
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        assert!(material.is_emissive());
+        let emission = material.emitted(&rec, &-ray.direction()).expect("expected emission");
+        let expected = 4.0 * (1.0 - fresnel::dielectric(1.0, 1.5));
+        assert!((emission.x - expected).abs() < 1e-4, "{:?}", emission);
+        // the tint is what a round trip keeps, a single pass keeps its square root
+        assert!((emission.y - 0.5 * expected).abs() < 1e-4, "{:?}", emission);
+        assert!(material.interior().is_none());
+
+        let skin: Arc<Box<dyn Material>> = Arc::new(Box::new(Subsurface::new(Vec3::new_diagonal(0.1), Vec3::new_diagonal(0.9), 0.0)));
+        let material = Coated::new(skin, 1.5, 0.0);
+        assert!(material.interior().is_some());
+        assert!(!material.is_emissive());
+    }
+
+    #[test]
+    fn test_keeps_eta() {
+        // light subpaths need the relative IOR of a refraction through the base
+        for roughness in [0.0, 0.3] {
+            let glass: Arc<Box<dyn Material>> = Arc::new(Box::new(RoughDielectric::new(1.5, roughness)));
+            let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Coated::new(glass, 1.3, 0.2)));
+            let ray = Ray::new(Vec3::new(0.5, 1.0, 0.2), Vec3::new(-0.5, -1.0, -0.2));
+            let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+            let transmitted: Vec<BsdfSample> = (0..200)
+                .filter_map(|_| material.sample(&rec, &-ray.direction()))
+                .filter(|sample| sample.lobe.contains(Lobe::TRANSMISSION))
+                .collect();
+            assert!(!transmitted.is_empty());
+            assert!(transmitted.iter().all(|sample| sample.eta == 1.5));
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{hittable::HitRecord, math::vec3::Vec3, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, fresnel, library::{to_array, ConductorIor, MaterialDesc, Roughness}, microfacet::{reflect, GGX}, Material};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConductorPreset {
+    Gold,
+    Copper,
+    Aluminium,
+    Silver,
+    Iron,
+}
+
+impl ConductorPreset {
+    // Complex IOR (eta, k) sampled at roughly 650, 550 and 450nm
+    pub fn ior(&self) -> (Vec3, Vec3) {
+        match self {
+            ConductorPreset::Gold => (Vec3::new(0.143, 0.374, 1.442), Vec3::new(3.983, 2.385, 1.603)),
+            ConductorPreset::Copper => (Vec3::new(0.200, 0.924, 1.102), Vec3::new(3.912, 2.452, 2.142)),
+            ConductorPreset::Aluminium => (Vec3::new(1.657, 0.880, 0.521), Vec3::new(9.224, 6.270, 4.837)),
+            ConductorPreset::Silver => (Vec3::new(0.155, 0.117, 0.138), Vec3::new(4.828, 3.122, 2.147)),
+            ConductorPreset::Iron => (Vec3::new(2.911, 2.950, 2.585), Vec3::new(3.089, 2.932, 2.767)),
+        }
+    }
+}
+
+// GGX microfacet conductor with Fresnel from a complex IOR.
+// Roughness is anisotropic along the surface tangent (u) and bitangent (v).
+#[derive(Clone)]
+pub struct Conductor {
+    eta: Vec3,
+    k: Vec3,
+    distribution: GGX,
+}
+
+impl Conductor {
+    pub fn new(eta: Vec3, k: Vec3, roughness: Float) -> Self {
+        Self::new_anisotropic(eta, k, roughness, roughness)
+    }
+
+    pub fn new_anisotropic(eta: Vec3, k: Vec3, roughness_u: Float, roughness_v: Float) -> Self {
+        Self { eta, k, distribution: GGX::from_roughness(roughness_u, roughness_v) }
+    }
+
+    pub fn new_preset(preset: ConductorPreset, roughness: Float) -> Self {
+        let (eta, k) = preset.ior();
+        Self::new(eta, k, roughness)
+    }
+
+    pub fn new_preset_anisotropic(preset: ConductorPreset, roughness_u: Float, roughness_v: Float) -> Self {
+        let (eta, k) = preset.ior();
+        Self::new_anisotropic(eta, k, roughness_u, roughness_v)
+    }
+
+    fn fresnel(&self, cos: Float) -> Vec3 {
+        fresnel::conductor(cos.abs(), self.eta, self.k)
+    }
+}
+
+impl Material for Conductor {
+    fn lobes(&self) -> Lobe {
+        if self.distribution.effectively_smooth() {
+            Lobe::SPECULAR | Lobe::REFLECTION
+        } else {
+            Lobe::GLOSSY | Lobe::REFLECTION
+        }
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if self.distribution.effectively_smooth() {
+            return Vec3::zero();
+        }
+        let frame = rec.shading_frame();
+        let (wo, wi) = (frame.to_local(*wo), frame.to_local(*wi));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vec3::zero();
+        }
+        let wm = wo + wi;
+        if wm.near_zero() {
+            return Vec3::zero();
+        }
+        let wm = wm.normalized();
+        let d = self.distribution.d(&wm);
+        let g = self.distribution.g(&wo, &wi);
+        self.fresnel(wo.dot(&wm)) * (d * g / (4.0 * wo.z * wi.z))
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let frame = rec.shading_frame();
+        let wo_local = frame.to_local(*wo);
+        if wo_local.z <= 0.0 {
+            return None;
+        }
+
+        if self.distribution.effectively_smooth() {
+            let wi = Vec3::new(-wo_local.x, -wo_local.y, wo_local.z);
+            return Some(BsdfSample::new(
+                frame.to_world(wi),
+                self.fresnel(wi.z),
+                1.0,
+                Lobe::SPECULAR | Lobe::REFLECTION,
+            ));
+        }
+
+        let wm = self.distribution.sample_wm(&wo_local);
+        let wi_local = reflect(&wo_local, &wm);
+        if wi_local.z <= 0.0 {
+            return None;
+        }
+        let wi = frame.to_world(wi_local);
+        let pdf = self.pdf(rec, wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let f = self.eval(rec, wo, &wi);
+        Some(BsdfSample::new(wi, f * wi_local.z / pdf, pdf, Lobe::GLOSSY | Lobe::REFLECTION))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        if self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+        let frame = rec.shading_frame();
+        let (wo, wi) = (frame.to_local(*wo), frame.to_local(*wi));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let wm = wo + wi;
+        if wm.near_zero() {
+            return 0.0;
+        }
+        let wm = wm.normalized();
+        self.distribution.d_visible(&wo, &wm) / (4.0 * wo.dot(&wm).abs())
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        let (u, v) = self.distribution.roughness();
+        Some(MaterialDesc::Conductor {
+            ior: ConductorIor::Complex { eta: to_array(self.eta), k: to_array(self.k) },
+            roughness: Roughness::from_pair(u, v),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{material::tests::assert_sample_consistent, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_consistency() {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            Conductor::new_preset_anisotropic(ConductorPreset::Gold, 0.3, 0.6)
+        ));
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 0.2), Vec3::new(-0.5, -1.0, -0.2));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone())
+            .with_tangent(Vec3::new(1.0, 0.0, 0.0));
+        let wo = -ray.direction();
+        for sample in assert_sample_consistent(&material, &rec, &wo, 1e-3) {
+            assert!(sample.wi.dot(&rec.normal) > 0.0);
+            // a passive surface never reflects more than it receives
+            assert!(sample.weight.x <= 1.0 + 1e-3);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use crate::{hittable::HitRecord, math::vec3::Vec3, texture::Texture, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, medium::Medium, Material};
+
+// Punches holes into a material where an opacity texture (first channel)
+// falls below the threshold, for leaves, fences and other alpha masked cards.
+// Geometry asks is_opaque() while intersecting and keeps traversing through holes,
+// so they cost no bounce and cast no shadow.
+#[derive(Clone)]
+pub struct Cutout {
+    base: Arc<Box<dyn Material>>,
+    opacity: Arc<Box<dyn Texture>>,
+    threshold: Float,
+}
+
+impl Cutout {
+    pub fn new(base: Arc<Box<dyn Material>>, opacity: Arc<Box<dyn Texture>>) -> Self {
+        Self { base, opacity, threshold: 0.5 }
+    }
+
+    pub fn with_threshold(mut self, threshold: Float) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl Material for Cutout {
+    fn lobes(&self) -> Lobe {
+        self.base.lobes()
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        self.base.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        self.base.sample(rec, wo)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        self.base.pdf(rec, wo, wi)
+    }
+
+    fn emitted(&self, rec: &HitRecord, wo: &Vec3) -> Option<Vec3> {
+        self.base.emitted(rec, wo)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.base.is_emissive()
+    }
+
+    fn interior(&self) -> Option<&Medium> {
+        self.base.interior()
+    }
+
+    fn is_opaque(&self, u: Float, v: Float, point: &Vec3) -> bool {
+        self.opacity.value(u, v, point).x >= self.threshold && self.base.is_opaque(u, v, point)
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Cutout {
+            base: Box::new(self.base.describe()?),
+            opacity: self.opacity.describe()?,
+            threshold: self.threshold,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hittable::{quad::Quad, sphere::Sphere, Hittable}, material::{lambertian::Lambertian, subsurface::Subsurface}, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_cutout_hit() {
+        let base: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::new_diagonal(0.8))));
+        // opaque for u < 0.5, transparent above
+        let mask: Arc<Box<dyn Texture>> = Arc::new(Box::new(StripeTexture));
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Cutout::new(base, mask)));
+
+        let quad = Quad::new(Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), material.clone());
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert!(quad.hit(&Ray::new(Vec3::new(0.25, 1.0, 0.5), down), 0.001..Float::INFINITY).is_some());
+        assert!(quad.hit(&Ray::new(Vec3::new(0.75, 1.0, 0.5), down), 0.001..Float::INFINITY).is_none());
+
+        // a hole in the near side of a sphere exposes the far side
+        let sphere = Sphere::new(Vec3::zero(), 1.0, material);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = sphere.hit(&ray, 0.001..Float::INFINITY).expect("expected a hit");
+        assert!((rec.t - 4.0).abs() < 1e-4);
+        assert!(rec.u < 0.5);
+    }
+
+    #[test]
+    fn test_keeps_interior() {
+        let skin: Arc<Box<dyn Material>> = Arc::new(Box::new(Subsurface::new(Vec3::new_diagonal(0.1), Vec3::new_diagonal(0.9), 0.0)));
+        let mask: Arc<Box<dyn Texture>> = Arc::new(Box::new(StripeTexture));
+        assert!(Cutout::new(skin, mask).interior().is_some());
+    }
+
+    struct StripeTexture;
+
+    impl Texture for StripeTexture {
+        fn value(&self, u: Float, _v: Float, _point: &Vec3) -> Vec3 {
+            if u < 0.5 { Vec3::new_diagonal(1.0) } else { Vec3::zero() }
+        }
+    }
+}
@@ -1,15 +1,21 @@
-use crate::{hittable::HitRecord, math::vec3::Vec3, utils::random::random, ray::Ray, Float};
+use std::sync::Arc;
 
-use super::Material;
+use crate::{hittable::HitRecord, math::vec3::Vec3, texture::{solid::SolidColor, Texture}, utils::random::random, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, Material};
 
 #[derive(Clone)]
 pub struct Dielectric {
-    albedo: Vec3,
+    albedo: Arc<Box<dyn Texture>>,
     refraction_index: Float,
 }
 
 impl Dielectric {
     pub fn new(albedo: Vec3, refraction_index: Float) -> Self {
+        Dielectric::new_with_texture(Arc::new(Box::new(SolidColor::new(albedo))), refraction_index)
+    }
+
+    pub fn new_with_texture(albedo: Arc<Box<dyn Texture>>, refraction_index: Float) -> Self {
         Dielectric { albedo, refraction_index }
     }
 
@@ -23,25 +29,46 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn lobes(&self) -> Lobe {
+        Lobe::SPECULAR | Lobe::REFLECTION | Lobe::TRANSMISSION
+    }
+
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
         let refraction_index = if hit_record.front_face {
             Float::from(1.0) / self.refraction_index
         } else {
             self.refraction_index
         };
 
-        let cos = (-hit_record.normal.dot(&ray.direction())).min(1.0);
+        let direction = -*wo;
+        let cos = (-hit_record.normal.dot(&direction)).min(1.0);
         let sin = (Float::from(1.0) - cos*cos).sqrt();
 
         let total_reflection = refraction_index * sin > 1.0;
-        let reflectance = Self::reflectance(cos, refraction_index);
-        let direction = if total_reflection || reflectance > random() {
-            ray.direction().reflect(&hit_record.normal)
+        let reflectance = if total_reflection {
+            Float::from(1.0)
         } else {
-            ray.direction().refract(hit_record.normal, refraction_index)
+            Self::reflectance(cos, refraction_index)
         };
+        let albedo = self.albedo.value(hit_record.u, hit_record.v, &hit_record.point);
+        if reflectance > random() {
+            Some(BsdfSample::new(
+                direction.reflect(&hit_record.normal),
+                albedo,
+                reflectance,
+                Lobe::SPECULAR | Lobe::REFLECTION,
+            ))
+        } else {
+            Some(BsdfSample::new(
+                direction.refract(hit_record.normal, refraction_index).normalized(),
+                albedo,
+                Float::from(1.0) - reflectance,
+                Lobe::SPECULAR | Lobe::TRANSMISSION,
+            ))
+        }
+    }
 
-        let refracted_ray = Ray::new(hit_record.point, direction);
-        Some((refracted_ray, self.albedo))
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Dielectric { albedo: self.albedo.describe()?, refraction_index: self.refraction_index })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{hittable::HitRecord, math::{onb::Onb, vec3::Vec3}, utils::{random::random, spectrum}, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, fresnel, library::MaterialDesc, microfacet::{reflect, refract}, Material};
+
+// Wavelength dependent index of refraction, wavelengths in nanometers
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Ior {
+    // n = a + b / lambda^2, lambda in micrometers
+    Cauchy { a: Float, b: Float },
+    // n^2 = 1 + sum(b_i lambda^2 / (lambda^2 - c_i)), lambda in micrometers
+    Sellmeier { b: [Float; 3], c: [Float; 3] },
+}
+
+impl Ior {
+    pub const BK7: Ior = Ior::Sellmeier {
+        b: [1.039_612, 0.231_792_34, 1.010_469_5],
+        c: [0.006_000_699, 0.020_017_914, 103.560_65],
+    };
+    pub const FUSED_SILICA: Ior = Ior::Sellmeier {
+        b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+        c: [0.004_679_148, 0.013_512_063, 97.934_0],
+    };
+    pub const DENSE_FLINT: Ior = Ior::Sellmeier {
+        b: [1.737_597, 0.313_747_35, 1.898_781],
+        c: [0.013_188_707, 0.062_306_814, 155.236_3],
+    };
+    pub const DIAMOND: Ior = Ior::Cauchy { a: 2.38, b: 0.0121 };
+
+    pub fn at(&self, wavelength: Float) -> Float {
+        let l2 = (wavelength * 1e-3).powi(2);
+        match self {
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => {
+                let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<Float>();
+                n2.max(1.0).sqrt()
+            }
+        }
+    }
+}
+
+// Smooth glass whose IOR depends on wavelength, so white light splits into a spectrum.
+// The first bounce off it picks a wavelength for the rest of the path and weights
+// the path by that wavelength's RGB response.
+#[derive(Clone)]
+pub struct DispersiveDielectric {
+    ior: Ior,
+}
+
+impl DispersiveDielectric {
+    pub fn new(ior: Ior) -> Self {
+        Self { ior }
+    }
+
+    fn frame(rec: &HitRecord) -> Onb {
+        let outward_normal = if rec.front_face { rec.normal } else { -rec.normal };
+        Onb::new(outward_normal)
+    }
+}
+
+impl Material for DispersiveDielectric {
+    fn lobes(&self) -> Lobe {
+        Lobe::SPECULAR | Lobe::REFLECTION | Lobe::TRANSMISSION
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let (wavelength, weight) = match rec.wavelength {
+            Some(wavelength) => (wavelength, Vec3::new_diagonal(1.0)),
+            None => {
+                let wavelength = spectrum::sample_wavelength();
+                (wavelength, spectrum::wavelength_to_rgb(wavelength))
+            }
+        };
+        let refraction_index = self.ior.at(wavelength);
+
+        let frame = Self::frame(rec);
+        let wo_local = frame.to_local(*wo);
+        if wo_local.z == 0.0 {
+            return None;
+        }
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let r = fresnel::dielectric(wo_local.z, refraction_index);
+        let sample = if random::<Float>() < r {
+            BsdfSample::new(
+                frame.to_world(reflect(&wo_local, &n)),
+                weight,
+                r,
+                Lobe::SPECULAR | Lobe::REFLECTION,
+            )
+        } else {
+            let wi = refract(&wo_local, &n, refraction_index)?;
+            let etap = if wo_local.z > 0.0 { refraction_index } else { 1.0 / refraction_index };
+            BsdfSample::new(
+                frame.to_world(wi),
+                weight / (etap * etap),
+                1.0 - r,
+                Lobe::SPECULAR | Lobe::TRANSMISSION,
+            ).with_eta(etap)
+        };
+        Some(sample.with_wavelength(Some(wavelength)))
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::DispersiveDielectric { ior: self.ior })
+    }
+}
+
+// Soap bubble: a film thin enough that light passes straight through it,
+// with reflection colored by interference between the film's two faces
+#[derive(Clone)]
+pub struct ThinFilm {
+    film_ior: Float,
+    // nanometers
+    thickness: Float,
+}
+
+impl ThinFilm {
+    pub fn new(film_ior: Float, thickness: Float) -> Self {
+        Self { film_ior, thickness }
+    }
+}
+
+impl Material for ThinFilm {
+    fn lobes(&self) -> Lobe {
+        Lobe::SPECULAR | Lobe::REFLECTION | Lobe::TRANSMISSION
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let cos = rec.normal.dot(wo);
+        let reflectance = |wavelength| fresnel::thin_film(cos, self.film_ior, self.thickness, 1.0, wavelength);
+        let r = match rec.wavelength {
+            Some(wavelength) => Vec3::new_diagonal(reflectance(wavelength)),
+            None => spectrum::integrate_rgb(reflectance),
+        };
+        let probability = ((r.x + r.y + r.z) / 3.0).clamp(0.01, 0.99);
+        if random::<Float>() < probability {
+            Some(BsdfSample::new(
+                (-*wo).reflect(&rec.normal),
+                r / probability,
+                probability,
+                Lobe::SPECULAR | Lobe::REFLECTION,
+            ))
+        } else {
+            Some(BsdfSample::new(
+                -*wo,
+                (Vec3::new_diagonal(1.0) - r) / (1.0 - probability),
+                1.0 - probability,
+                Lobe::SPECULAR | Lobe::TRANSMISSION,
+            ))
+        }
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::ThinFilm { film_ior: self.film_ior, thickness: self.thickness })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::ray::Ray;
+
+    use super::*;
+
+    #[test]
+    fn test_dispersion() {
+        assert!(Ior::BK7.at(450.0) > Ior::BK7.at(650.0));
+        assert!((Ior::BK7.at(587.6) - 1.5168).abs() < 1e-3);
+
+        // a path that already carries a wavelength keeps it and isn't re-weighted
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(DispersiveDielectric::new(Ior::DENSE_FLINT)));
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+        let mut rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        rec.wavelength = Some(450.0);
+        let mut blue = Vec3::zero();
+        let mut red = Vec3::zero();
+        for _ in 0..100 {
+            let sample = material.sample(&rec, &-ray.direction()).expect("expected a sample");
+            assert_eq!(sample.wavelength, Some(450.0));
+            if sample.lobe.contains(Lobe::TRANSMISSION) {
+                assert_eq!(sample.eta, Ior::DENSE_FLINT.at(450.0));
+                blue = sample.wi;
+            }
+        }
+        rec.wavelength = Some(650.0);
+        for _ in 0..100 {
+            let sample = material.sample(&rec, &-ray.direction()).expect("expected a sample");
+            if sample.lobe.contains(Lobe::TRANSMISSION) {
+                red = sample.wi;
+            }
+        }
+        // blue bends more towards the normal
+        assert!(blue.y.abs() > red.y.abs());
+    }
+}
@@ -0,0 +1,169 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{math::vec3::Vec3, Float, FloatConsts};
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: Float,
+    im: Float,
+}
+
+impl Complex {
+    fn new(re: Float, im: Float) -> Self {
+        Self { re, im }
+    }
+
+    fn norm(&self) -> Float {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn sqrt(&self) -> Self {
+        let n = self.norm().sqrt();
+        if n == 0.0 {
+            return Complex::new(0.0, 0.0);
+        }
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0.0 {
+            Complex::new(t1, t2)
+        } else {
+            Complex::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        let scale = Float::from(1.0) / rhs.norm();
+        Complex::new(
+            scale * (self.re * rhs.re + self.im * rhs.im),
+            scale * (self.im * rhs.re - self.re * rhs.im),
+        )
+    }
+}
+
+// Unpolarized reflectance of a dielectric interface.
+// eta is the relative IOR (transmitted side over incident side) seen from the
+// side the normal points to; a negative cos_i means the light arrives from inside.
+pub fn dielectric(cos_i: Float, eta: Float) -> Float {
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i.max(-1.0), Float::from(1.0) / eta)
+    } else {
+        (cos_i.min(1.0), eta)
+    };
+    let sin2_i = Float::from(1.0) - cos_i * cos_i;
+    let sin2_t = sin2_i / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (Float::from(1.0) - sin2_t).max(0.0).sqrt();
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parl * r_parl + r_perp * r_perp)
+}
+
+fn complex(cos_i: Float, eta: Complex) -> Float {
+    let cos_i = Complex::new(cos_i.clamp(0.0, 1.0), 0.0);
+    let one = Complex::new(1.0, 0.0);
+    let sin2_i = one - cos_i * cos_i;
+    let sin2_t = sin2_i / (eta * eta);
+    let cos_t = (one - sin2_t).sqrt();
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parl.norm() + r_perp.norm())
+}
+
+// Per channel reflectance of a conductor with complex IOR eta + i*k
+pub fn conductor(cos_i: Float, eta: Vec3, k: Vec3) -> Vec3 {
+    Vec3::new(
+        complex(cos_i, Complex::new(eta.x, k.x)),
+        complex(cos_i, Complex::new(eta.y, k.y)),
+        complex(cos_i, Complex::new(eta.z, k.z)),
+    )
+}
+
+// Reflectance of a film of `film_ior` and `thickness` (nm) between air and a
+// substrate of `substrate_ior`, at one wavelength (nm). Airy summation of the
+// waves bouncing inside the film, averaged over both polarizations.
+pub fn thin_film(cos_i: Float, film_ior: Float, thickness: Float, substrate_ior: Float, wavelength: Float) -> Float {
+    let cos_i = cos_i.abs().min(1.0);
+    let sin2_i = Float::from(1.0) - cos_i * cos_i;
+    let sin2_substrate = sin2_i / (substrate_ior * substrate_ior);
+    if sin2_substrate >= 1.0 {
+        return 1.0;
+    }
+    let cos_film = (Float::from(1.0) - sin2_i / (film_ior * film_ior)).max(0.0).sqrt();
+    let cos_substrate = (Float::from(1.0) - sin2_substrate).sqrt();
+
+    let phase = 4.0 * FloatConsts::PI * film_ior * thickness * cos_film / wavelength;
+    let airy = |r12: Float, r23: Float| {
+        let cross = 2.0 * r12 * r23 * phase.cos();
+        (r12 * r12 + r23 * r23 + cross) / (1.0 + r12 * r12 * r23 * r23 + cross)
+    };
+    let (n1, n2, n3) = (Float::from(1.0), film_ior, substrate_ior);
+    let r12_perp = (n1 * cos_i - n2 * cos_film) / (n1 * cos_i + n2 * cos_film);
+    let r23_perp = (n2 * cos_film - n3 * cos_substrate) / (n2 * cos_film + n3 * cos_substrate);
+    let r12_parl = (n2 * cos_i - n1 * cos_film) / (n2 * cos_i + n1 * cos_film);
+    let r23_parl = (n3 * cos_film - n2 * cos_substrate) / (n3 * cos_film + n2 * cos_substrate);
+    0.5 * (airy(r12_perp, r23_perp) + airy(r12_parl, r23_parl))
+}
+
+pub fn schlick(cos_i: Float, f0: Vec3) -> Vec3 {
+    let m = (Float::from(1.0) - cos_i.clamp(0.0, 1.0)).powi(5);
+    f0 + m * (Vec3::new_diagonal(1.0) - f0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normal_incidence() {
+        let f = dielectric(1.0, 1.5);
+        assert!((f - 0.04).abs() < 1e-5);
+        assert!((dielectric(-1.0, 1.5) - 0.04).abs() < 1e-5);
+        assert_eq!(dielectric(-0.1, 1.5), 1.0);
+
+        let (n, k) = (0.2, 3.9);
+        let expected = ((n - 1.0) * (n - 1.0) + k * k) / ((n + 1.0) * (n + 1.0) + k * k);
+        let f = conductor(1.0, Vec3::new_diagonal(n), Vec3::new_diagonal(k));
+        assert!((f.x - expected).abs() < 1e-4);
+        assert!((conductor(0.0, Vec3::new_diagonal(n), Vec3::new_diagonal(k)).x - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_thin_film() {
+        // a vanishing film is just the bare substrate
+        let bare = thin_film(0.8, 1.33, 0.0, 1.5, 550.0);
+        assert!((bare - dielectric(0.8, 1.5)).abs() < 1e-4);
+        // a quarter wave film on a denser substrate is an anti-reflection coating
+        let n = 1.5_f32.sqrt();
+        let coated = thin_film(1.0, n, 550.0 / (4.0 * n), 1.5, 550.0);
+        assert!(coated < 1e-4);
+    }
+}
@@ -1,23 +1,77 @@
-use super::Material;
-use crate::{hittable::HitRecord, math::vec3::Vec3, utils::random, ray::Ray};
+use std::sync::Arc;
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, same_hemisphere, Material};
+use crate::{hittable::HitRecord, math::{onb::Onb, vec3::Vec3}, texture::{solid::SolidColor, Texture}, Float, FloatConsts};
 
 #[derive(Clone)]
 pub struct Lambertian {
-    albedo: Vec3,
+    albedo: Arc<Box<dyn Texture>>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Vec3) -> Lambertian {
+        Lambertian::new_with_texture(Arc::new(Box::new(SolidColor::new(albedo))))
+    }
+
+    pub fn new_with_texture(albedo: Arc<Box<dyn Texture>>) -> Lambertian {
         Lambertian { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
-        let mut scatter_direction = rec.normal + Vec3::new_random_unit_vector();
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
+    fn lobes(&self) -> Lobe {
+        Lobe::DIFFUSE | Lobe::REFLECTION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if !same_hemisphere(&rec.normal, wo, wi) {
+            return Vec3::zero();
+        }
+        self.albedo.value(rec.u, rec.v, &rec.point) / FloatConsts::PI
+    }
+
+    fn sample(&self, rec: &HitRecord, _wo: &Vec3) -> Option<BsdfSample> {
+        let onb = Onb::new(rec.normal);
+        let local = Vec3::new_random_cosine_direction();
+        if local.z <= 0.0 {
+            return None;
+        }
+        let wi = onb.to_world(local);
+        Some(BsdfSample::new(
+            wi,
+            self.albedo.value(rec.u, rec.v, &rec.point),
+            local.z / FloatConsts::PI,
+            Lobe::DIFFUSE | Lobe::REFLECTION,
+        ))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        if !same_hemisphere(&rec.normal, wo, wi) {
+            return 0.0;
         }
-        Some((Ray::new(rec.point, scatter_direction), self.albedo.clone()))
+        rec.normal.dot(wi).abs() / FloatConsts::PI
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Lambertian { albedo: self.albedo.describe()? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::tests::assert_sample_consistent, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_consistency() {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::new(0.5, 0.25, 1.0))));
+        let ray = Ray::new(Vec3::new(0.3, 1.0, 0.2), Vec3::new(-0.3, -1.0, -0.2));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        let wo = -ray.direction();
+        assert_sample_consistent(&material, &rec, &wo, 1e-4);
+        let below = Vec3::new(0.0, -1.0, 0.0);
+        assert_eq!(material.eval(&rec, &wo, &below), Vec3::zero());
+        assert_eq!(material.pdf(&rec, &wo, &below), 0.0);
     }
 }
@@ -0,0 +1,331 @@
+use std::{collections::BTreeMap, fs, io::{self, ErrorKind}, path::{Path, PathBuf}, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{math::vec3::Vec3, texture::{checker::{CheckerMode, CheckerTexture}, image::{FilterMode, ImageTexture, WrapMode}, procedural::{GraniteTexture, MarbleTexture, NoiseTexture, WoodTexture}, solid::SolidColor, Texture}, Float};
+
+use super::{cloth::Cloth, coated::Coated, conductor::{Conductor, ConductorPreset}, cutout::Cutout, dielectric::Dielectric, dispersive::{DispersiveDielectric, Ior, ThinFilm}, lambertian::Lambertian, light::Light, metal::Metal, mix::Mix, normalmap::NormalMapped, orennayar::OrenNayar, principled::Principled, roughdielectric::RoughDielectric, subsurface::Subsurface, Material};
+
+// Data file form of materials and textures, so look-dev can edit them without
+// recompiling. A library file is a JSON object mapping names to materials:
+//
+// { "floor": { "type": "lambertian", "albedo": [0.8, 0.8, 0.8] },
+//   "gold": { "type": "conductor", "preset": "gold", "roughness": 0.3 } }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum TextureDesc {
+    Color([Float; 3]),
+    Value(Float),
+    Texture(TextureKind),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureKind {
+    Checker { mode: CheckerMode, scale: Float, even: Box<TextureDesc>, odd: Box<TextureDesc> },
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        linear: bool,
+        #[serde(default = "default_wrap_mode")]
+        wrap: WrapMode,
+        #[serde(default = "default_filter_mode")]
+        filter: FilterMode,
+    },
+    Noise { seed: u64, scale: Float, octaves: usize },
+    Marble { seed: u64, scale: Float, base: [Float; 3], vein: [Float; 3], distortion: Float },
+    Wood { seed: u64, scale: Float, rings: Float, light: [Float; 3], dark: [Float; 3] },
+    Granite { seed: u64, scale: Float, base: [Float; 3], speckle: [Float; 3] },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Roughness {
+    Isotropic(Float),
+    Anisotropic([Float; 2]),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ConductorIor {
+    Preset { preset: ConductorPreset },
+    Complex { eta: [Float; 3], k: [Float; 3] },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDesc {
+    Lambertian { albedo: TextureDesc },
+    OrenNayar { albedo: TextureDesc, sigma: Float },
+    Metal { albedo: TextureDesc, fuzz: Float },
+    Dielectric {
+        #[serde(default = "white")]
+        albedo: TextureDesc,
+        refraction_index: Float,
+    },
+    Light {
+        color: TextureDesc,
+        #[serde(default = "default_true")]
+        two_sided: bool,
+        #[serde(default)]
+        falloff: Float,
+    },
+    Conductor {
+        #[serde(flatten)]
+        ior: ConductorIor,
+        roughness: Roughness,
+    },
+    RoughDielectric {
+        refraction_index: Float,
+        roughness: Roughness,
+        #[serde(default)]
+        absorption: [Float; 3],
+    },
+    Principled(Box<PrincipledDesc>),
+    Mix { a: Box<MaterialDesc>, b: Box<MaterialDesc>, weight: TextureDesc },
+    Coated {
+        base: Box<MaterialDesc>,
+        refraction_index: Float,
+        roughness: Float,
+        #[serde(default = "default_tint")]
+        tint: [Float; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        film: Option<FilmDesc>,
+    },
+    Cloth { albedo: TextureDesc, sheen: TextureDesc, roughness: Float },
+    DispersiveDielectric { ior: Ior },
+    ThinFilm { film_ior: Float, thickness: Float },
+    NormalMap { base: Box<MaterialDesc>, map: TextureDesc },
+    BumpMap { base: Box<MaterialDesc>, height: TextureDesc, scale: Float },
+    Cutout {
+        base: Box<MaterialDesc>,
+        opacity: TextureDesc,
+        #[serde(default = "default_threshold")]
+        threshold: Float,
+    },
+    Subsurface {
+        mean_free_path: [Float; 3],
+        albedo: [Float; 3],
+        #[serde(default)]
+        anisotropy: Float,
+        #[serde(default = "default_subsurface_ior")]
+        refraction_index: Float,
+        #[serde(default)]
+        roughness: Float,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PrincipledDesc {
+    pub base_color: TextureDesc,
+    #[serde(default = "zero")]
+    pub metallic: TextureDesc,
+    #[serde(default = "half")]
+    pub roughness: TextureDesc,
+    #[serde(default = "half")]
+    pub specular: TextureDesc,
+    #[serde(default = "zero")]
+    pub sheen: TextureDesc,
+    #[serde(default = "half")]
+    pub sheen_tint: TextureDesc,
+    #[serde(default = "zero")]
+    pub clearcoat: TextureDesc,
+    #[serde(default = "default_clearcoat_roughness")]
+    pub clearcoat_roughness: TextureDesc,
+    #[serde(default = "zero")]
+    pub transmission: TextureDesc,
+    #[serde(default = "default_refraction_index")]
+    pub refraction_index: Float,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FilmDesc {
+    pub ior: Float,
+    // nanometers
+    pub thickness: Float,
+}
+
+fn default_true() -> bool { true }
+fn zero() -> TextureDesc { TextureDesc::Value(0.0) }
+fn half() -> TextureDesc { TextureDesc::Value(0.5) }
+fn white() -> TextureDesc { TextureDesc::Value(1.0) }
+fn default_clearcoat_roughness() -> TextureDesc { TextureDesc::Value(0.1) }
+fn default_refraction_index() -> Float { 1.5 }
+fn default_subsurface_ior() -> Float { 1.4 }
+fn default_tint() -> [Float; 3] { [1.0, 1.0, 1.0] }
+fn default_threshold() -> Float { 0.5 }
+fn default_wrap_mode() -> WrapMode { WrapMode::Repeat }
+fn default_filter_mode() -> FilterMode { FilterMode::Bilinear }
+
+pub(crate) fn to_array(v: Vec3) -> [Float; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn to_vec3(a: [Float; 3]) -> Vec3 {
+    Vec3::new(a[0], a[1], a[2])
+}
+
+impl Roughness {
+    pub(crate) fn from_pair(u: Float, v: Float) -> Self {
+        if u == v { Roughness::Isotropic(u) } else { Roughness::Anisotropic([u, v]) }
+    }
+
+    fn pair(&self) -> (Float, Float) {
+        match *self {
+            Roughness::Isotropic(r) => (r, r),
+            Roughness::Anisotropic([u, v]) => (u, v),
+        }
+    }
+}
+
+impl TextureDesc {
+    pub fn build(&self) -> io::Result<Arc<Box<dyn Texture>>> {
+        let texture: Box<dyn Texture> = match self {
+            TextureDesc::Color(color) => Box::new(SolidColor::new(to_vec3(*color))),
+            TextureDesc::Value(value) => Box::new(SolidColor::new(Vec3::new_diagonal(*value))),
+            TextureDesc::Texture(kind) => match kind {
+                TextureKind::Checker { mode, scale, even, odd } => {
+                    Box::new(CheckerTexture::new(*mode, *scale, even.build()?, odd.build()?))
+                }
+                TextureKind::Image { path, linear, wrap, filter } => {
+                    let image = if *linear { ImageTexture::open_linear(path) } else { ImageTexture::open(path) };
+                    let image = image.map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+                    Box::new(image.with_wrap_mode(*wrap).with_filter_mode(*filter))
+                }
+                TextureKind::Noise { seed, scale, octaves } => Box::new(NoiseTexture::new(*seed, *scale, *octaves)),
+                TextureKind::Marble { seed, scale, base, vein, distortion } => Box::new(
+                    MarbleTexture::new(*seed, *scale, to_vec3(*base), to_vec3(*vein)).with_distortion(*distortion)
+                ),
+                TextureKind::Wood { seed, scale, rings, light, dark } => {
+                    Box::new(WoodTexture::new(*seed, *scale, *rings, to_vec3(*light), to_vec3(*dark)))
+                }
+                TextureKind::Granite { seed, scale, base, speckle } => {
+                    Box::new(GraniteTexture::new(*seed, *scale, to_vec3(*base), to_vec3(*speckle)))
+                }
+            },
+        };
+        Ok(Arc::new(texture))
+    }
+}
+
+impl MaterialDesc {
+    pub fn build(&self) -> io::Result<Box<dyn Material>> {
+        let shared = |desc: &MaterialDesc| -> io::Result<Arc<Box<dyn Material>>> { Ok(Arc::new(desc.build()?)) };
+        let material: Box<dyn Material> = match self {
+            MaterialDesc::Lambertian { albedo } => Box::new(Lambertian::new_with_texture(albedo.build()?)),
+            MaterialDesc::OrenNayar { albedo, sigma } => Box::new(OrenNayar::new_with_texture(albedo.build()?, *sigma)),
+            MaterialDesc::Metal { albedo, fuzz } => Box::new(Metal::new_with_texture(albedo.build()?, *fuzz)),
+            MaterialDesc::Dielectric { albedo, refraction_index } => {
+                Box::new(Dielectric::new_with_texture(albedo.build()?, *refraction_index))
+            }
+            MaterialDesc::Light { color, two_sided, falloff } => {
+                let light = Light::new_with_texture(color.build()?).with_falloff(*falloff);
+                Box::new(if *two_sided { light } else { light.with_one_sided() })
+            }
+            MaterialDesc::Conductor { ior, roughness } => {
+                let (eta, k) = match ior {
+                    ConductorIor::Preset { preset } => preset.ior(),
+                    ConductorIor::Complex { eta, k } => (to_vec3(*eta), to_vec3(*k)),
+                };
+                let (u, v) = roughness.pair();
+                Box::new(Conductor::new_anisotropic(eta, k, u, v))
+            }
+            MaterialDesc::RoughDielectric { refraction_index, roughness, absorption } => {
+                let (u, v) = roughness.pair();
+                Box::new(RoughDielectric::new_anisotropic(*refraction_index, u, v).with_absorption(to_vec3(*absorption)))
+            }
+            MaterialDesc::Principled(desc) => Box::new(
+                Principled::new_with_texture(desc.base_color.build()?)
+                    .with_metallic_texture(desc.metallic.build()?)
+                    .with_roughness_texture(desc.roughness.build()?)
+                    .with_specular_texture(desc.specular.build()?)
+                    .with_sheen_texture(desc.sheen.build()?, desc.sheen_tint.build()?)
+                    .with_clearcoat_texture(desc.clearcoat.build()?, desc.clearcoat_roughness.build()?)
+                    .with_transmission_texture(desc.transmission.build()?, desc.refraction_index)
+            ),
+            MaterialDesc::Mix { a, b, weight } => Box::new(Mix::new_with_texture(shared(a)?, shared(b)?, weight.build()?)),
+            MaterialDesc::Coated { base, refraction_index, roughness, tint, film } => {
+                let coated = Coated::new(shared(base)?, *refraction_index, *roughness).with_tint(to_vec3(*tint));
+                Box::new(match film {
+                    Some(film) => coated.with_thin_film(film.ior, film.thickness),
+                    None => coated,
+                })
+            }
+            MaterialDesc::Cloth { albedo, sheen, roughness } => {
+                Box::new(Cloth::new_with_texture(albedo.build()?, sheen.build()?, *roughness))
+            }
+            MaterialDesc::DispersiveDielectric { ior } => Box::new(DispersiveDielectric::new(*ior)),
+            MaterialDesc::ThinFilm { film_ior, thickness } => Box::new(ThinFilm::new(*film_ior, *thickness)),
+            MaterialDesc::NormalMap { base, map } => Box::new(NormalMapped::new_normal_map(shared(base)?, map.build()?)),
+            MaterialDesc::BumpMap { base, height, scale } => {
+                Box::new(NormalMapped::new_bump_map(shared(base)?, height.build()?, *scale))
+            }
+            MaterialDesc::Cutout { base, opacity, threshold } => {
+                Box::new(Cutout::new(shared(base)?, opacity.build()?).with_threshold(*threshold))
+            }
+            MaterialDesc::Subsurface { mean_free_path, albedo, anisotropy, refraction_index, roughness } => Box::new(
+                Subsurface::new(to_vec3(*mean_free_path), to_vec3(*albedo), *anisotropy)
+                    .with_boundary(*refraction_index, *roughness)
+            ),
+        };
+        Ok(material)
+    }
+}
+
+pub fn read_library(path: &Path) -> io::Result<BTreeMap<String, MaterialDesc>> {
+    let text = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+pub fn write_library(path: &Path, library: &BTreeMap<String, MaterialDesc>) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(library)?;
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hittable::world::World;
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let text = r#"{
+            "floor": { "type": "lambertian", "albedo": { "type": "checker", "mode": "uv", "scale": 8.0, "even": 0.9, "odd": [0.1, 0.1, 0.1] } },
+            "gold": { "type": "conductor", "preset": "gold", "roughness": 0.3 },
+            "paint": { "type": "coated", "base": { "type": "principled", "base_color": [0.6, 0.0, 0.0] }, "refraction_index": 1.5, "roughness": 0.05 },
+            "prism": { "type": "dispersive_dielectric", "ior": { "model": "sellmeier", "b": [1.0, 0.2, 1.0], "c": [0.006, 0.02, 103.0] } },
+            "lamp": { "type": "light", "color": [4.0, 4.0, 4.0], "two_sided": false }
+        }"#;
+        let library: BTreeMap<String, MaterialDesc> = serde_json::from_str(text).expect("failed to parse library");
+        assert_eq!(library.len(), 5);
+
+        for (name, desc) in &library {
+            let material = desc.build().expect("failed to build material");
+            let described = material.describe().unwrap_or_else(|| panic!("{} can't be described", name));
+            assert_eq!(described.build().expect("failed to rebuild").describe(), Some(described.clone()));
+        }
+        let lamp = library["lamp"].build().unwrap().describe().unwrap();
+        assert_eq!(lamp, library["lamp"]);
+    }
+
+    #[test]
+    fn test_world_library() {
+        let path = std::env::temp_dir().join(format!("raytracer-library-{}.json", std::process::id()));
+        let mut world = World::new();
+        world.add_material("white", Box::new(Lambertian::new(Vec3::new_diagonal(0.8))));
+        world.add_material("glass", Box::new(RoughDielectric::new(1.5, 0.2)));
+        world.save_materials(&path).expect("failed to save materials");
+
+        let mut loaded = World::new();
+        loaded.load_materials(&path).expect("failed to load materials");
+        for name in ["white", "glass"] {
+            let original = world.get_material(name).unwrap().describe();
+            assert_eq!(loaded.get_material(name).unwrap().describe(), original);
+        }
+        // loading the same names twice is refused instead of panicking
+        assert!(loaded.load_materials(&path).is_err());
+        fs::remove_file(&path).ok();
+    }
+}
@@ -1,24 +1,98 @@
-use crate::{hittable::HitRecord, math::vec3::Vec3, ray::Ray};
+use std::sync::Arc;
 
-use super::Material;
+use crate::{hittable::HitRecord, math::vec3::Vec3, texture::{solid::SolidColor, Texture}, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, Material};
 
 #[derive(Clone)]
 pub struct Light {
-    color: Vec3,
+    color: Arc<Box<dyn Texture>>,
+    two_sided: bool,
+    falloff: Float,
 }
 
 impl Light {
     pub fn new(color: Vec3) -> Self {
-        Self { color }
+        Self::new_with_texture(Arc::new(Box::new(SolidColor::new(color))))
+    }
+
+    // Screens, signage and other emitters showing an image
+    pub fn new_with_texture(color: Arc<Box<dyn Texture>>) -> Self {
+        Self { color, two_sided: true, falloff: 0.0 }
+    }
+
+    // Emit only from the side the outward normal points to
+    pub fn with_one_sided(mut self) -> Self {
+        self.two_sided = false;
+        self
+    }
+
+    // Scales emission by cos^falloff of the angle to the normal:
+    // 0 emits evenly, higher values focus the light into a spotlight-like beam
+    pub fn with_falloff(mut self, falloff: Float) -> Self {
+        self.falloff = falloff;
+        self
     }
 }
 
 impl Material for Light {
-    fn scatter(&self, _ray: &Ray, _hit_record: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn lobes(&self) -> Lobe {
+        Lobe::NONE
+    }
+
+    fn sample(&self, _hit_record: &HitRecord, _wo: &Vec3) -> Option<BsdfSample> {
         None
     }
 
-    fn emitted(&self) -> Option<Vec3> {
-        Some(self.color)
+    fn emitted(&self, rec: &HitRecord, wo: &Vec3) -> Option<Vec3> {
+        if !self.two_sided && !rec.front_face {
+            return None;
+        }
+        let color = self.color.value(rec.u, rec.v, &rec.point);
+        if self.falloff == 0.0 {
+            return Some(color);
+        }
+        let cos = rec.geometric_normal.dot(wo).max(0.0);
+        Some(color * cos.powf(self.falloff))
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
     }
-}
\ No newline at end of file
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Light { color: self.color.describe()?, two_sided: self.two_sided, falloff: self.falloff })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ray::Ray;
+
+    use super::*;
+
+    #[test]
+    fn test_emission() {
+        let constant: Arc<Box<dyn Material>> = Arc::new(Box::new(Light::new(Vec3::new(4.0, 2.0, 1.0))));
+        let one_sided: Arc<Box<dyn Material>> = Arc::new(Box::new(Light::new(Vec3::new_diagonal(1.0)).with_one_sided()));
+        let focused: Arc<Box<dyn Material>> = Arc::new(Box::new(Light::new(Vec3::new_diagonal(1.0)).with_falloff(8.0)));
+
+        let front = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let back = Ray::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let grazing = Ray::new(Vec3::new(-1.0, 0.2, 0.0), Vec3::new(1.0, -0.2, 0.0));
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let hit = |ray: &Ray, material: &Arc<Box<dyn Material>>| {
+            let rec = HitRecord::new(ray, 1.0, normal, (0.5, 0.5), material.clone());
+            material.emitted(&rec, &-ray.direction())
+        };
+
+        assert_eq!(hit(&front, &constant), Some(Vec3::new(4.0, 2.0, 1.0)));
+        assert_eq!(hit(&back, &constant), Some(Vec3::new(4.0, 2.0, 1.0)));
+        assert!(hit(&front, &one_sided).is_some());
+        assert!(hit(&back, &one_sided).is_none());
+        let head_on = hit(&front, &focused).unwrap();
+        let side = hit(&grazing, &focused).unwrap();
+        assert!((head_on.x - 1.0).abs() < 1e-5);
+        assert!(side.x < 0.01);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{math::{onb::Onb, vec3::Vec3}, utils::random::random, Float, FloatConsts};
+
+// Homogeneous scattering medium filling the inside of an object
+#[derive(Clone, Copy)]
+pub struct Medium {
+    // extinction coefficient per channel, the inverse of the mean free path
+    sigma_t: Vec3,
+    // single scattering albedo per channel
+    albedo: Vec3,
+    // Henyey-Greenstein anisotropy, > 0 scatters forward
+    g: Float,
+}
+
+impl Medium {
+    pub fn new(mean_free_path: Vec3, albedo: Vec3, g: Float) -> Self {
+        let sigma_t = Vec3::new(
+            1.0 / mean_free_path.x.max(1e-6),
+            1.0 / mean_free_path.y.max(1e-6),
+            1.0 / mean_free_path.z.max(1e-6),
+        );
+        Self { sigma_t, albedo, g: g.clamp(-0.99, 0.99) }
+    }
+
+    // Mean free path, albedo and anisotropy this medium was created with
+    pub fn parameters(&self) -> (Vec3, Vec3, Float) {
+        let mean_free_path = Vec3::new(1.0 / self.sigma_t.x, 1.0 / self.sigma_t.y, 1.0 / self.sigma_t.z);
+        (mean_free_path, self.albedo, self.g)
+    }
+
+    fn transmittance(&self, distance: Float) -> Vec3 {
+        let tau = self.sigma_t * distance;
+        Vec3::new((-tau.x).exp(), (-tau.y).exp(), (-tau.z).exp())
+    }
+
+    // Samples how far a ray travels before scattering, given the distance to
+    // the boundary. Returns the scattering distance (None when the boundary is
+    // reached first) and the path weight of the step. The distance is drawn
+    // with the extinction of a random channel; the weight divides by the
+    // average density over all channels.
+    pub fn sample_distance(&self, boundary: Float) -> (Option<Float>, Vec3) {
+        let channel = ((random::<Float>() * 3.0) as usize).min(2);
+        let t = -(1.0 - random::<Float>()).ln() / self.sigma_t[channel];
+        if t < boundary {
+            let transmittance = self.transmittance(t);
+            let density = self.sigma_t * transmittance;
+            let pdf = (density.x + density.y + density.z) / 3.0;
+            (Some(t), self.albedo * density / pdf)
+        } else {
+            let transmittance = self.transmittance(boundary);
+            let pdf = (transmittance.x + transmittance.y + transmittance.z) / 3.0;
+            (None, transmittance / pdf)
+        }
+    }
+
+    // New direction after scattering off a ray travelling along `direction`.
+    // The phase function is sampled exactly, so the weight is one.
+    pub fn sample_direction(&self, direction: &Vec3) -> Vec3 {
+        let g = self.g;
+        let xi: Float = random();
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * FloatConsts::PI * random::<Float>();
+        Onb::new(*direction).to_world(Vec3::new(sin * phi.cos(), sin * phi.sin(), cos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transmittance_estimate() {
+        // without scattering, the expected weight is the transmittance to the boundary
+        let medium = Medium::new(Vec3::new(1.0, 0.5, 2.0), Vec3::zero(), 0.0);
+        let n = 50000;
+        let mut sum = Vec3::zero();
+        for _ in 0..n {
+            let (t, weight) = medium.sample_distance(1.0);
+            if t.is_none() {
+                sum += weight;
+            }
+        }
+        let estimate = sum / n as Float;
+        assert!((estimate - medium.transmittance(1.0)).length() < 0.02, "estimate {}", estimate);
+    }
+
+    #[test]
+    fn test_anisotropy() {
+        let medium = Medium::new(Vec3::new_diagonal(1.0), Vec3::new_diagonal(1.0), 0.7);
+        let direction = Vec3::new(0.0, 0.0, 1.0);
+        let n = 50000;
+        let mut mean_cos = 0.0;
+        for _ in 0..n {
+            mean_cos += medium.sample_direction(&direction).dot(&direction);
+        }
+        mean_cos /= n as Float;
+        assert!((mean_cos - 0.7).abs() < 0.02);
+    }
+}
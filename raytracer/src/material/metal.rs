@@ -1,26 +1,44 @@
-use crate::{hittable::HitRecord, math::vec3::Vec3, ray::Ray, Float};
+use std::sync::Arc;
 
-use super::Material;
+use crate::{hittable::HitRecord, math::vec3::Vec3, texture::{solid::SolidColor, Texture}, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, Material};
 
 #[derive(Clone)]
 pub struct Metal {
-    albedo: Vec3,
+    albedo: Arc<Box<dyn Texture>>,
     fuzz: Float,
 }
 
 impl Metal {
     pub fn new(albedo: Vec3, fuzz: Float) -> Metal {
+        Metal::new_with_texture(Arc::new(Box::new(SolidColor::new(albedo))), fuzz)
+    }
+
+    pub fn new_with_texture(albedo: Arc<Box<dyn Texture>>, fuzz: Float) -> Metal {
         Metal { albedo, fuzz: fuzz.clamp(Float::from(0.0), Float::from(1.0)) }
     }
 }
 
+// The fuzzed mirror has no closed form density,
+// so it is treated as a specular lobe even when fuzz > 0.
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Vec3)> {
-        let reflected = ray.direction().reflect(&rec.normal);
-        let scattered = Ray::new(
-            rec.point,
-            reflected + self.fuzz * Vec3::new_random_in_unit_sphere(),
-        );
-        Some((scattered, self.albedo.clone()))
+    fn lobes(&self) -> Lobe {
+        Lobe::SPECULAR | Lobe::REFLECTION
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let reflected = (-*wo).reflect(&rec.normal);
+        let wi = (reflected + self.fuzz * Vec3::new_random_in_unit_sphere()).normalized();
+        Some(BsdfSample::new(
+            wi,
+            self.albedo.value(rec.u, rec.v, &rec.point),
+            1.0,
+            Lobe::SPECULAR | Lobe::REFLECTION,
+        ))
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Metal { albedo: self.albedo.describe()?, fuzz: self.fuzz })
     }
 }
@@ -0,0 +1,140 @@
+use crate::{math::vec3::Vec3, utils::random::random, Float, FloatConsts};
+
+// Trowbridge-Reitz (GGX) distribution in a local frame where z is the normal
+#[derive(Clone, Copy)]
+pub struct GGX {
+    alpha_x: Float,
+    alpha_y: Float,
+}
+
+impl GGX {
+    pub fn new(alpha_x: Float, alpha_y: Float) -> Self {
+        Self { alpha_x, alpha_y }
+    }
+
+    // Perceptually linear roughness in [0, 1] to alpha
+    pub fn from_roughness(roughness_u: Float, roughness_v: Float) -> Self {
+        let to_alpha = |r: Float| {
+            let r = r.clamp(0.0, 1.0);
+            r * r
+        };
+        Self::new(to_alpha(roughness_u), to_alpha(roughness_v))
+    }
+
+    pub fn roughness(&self) -> (Float, Float) {
+        (self.alpha_x.sqrt(), self.alpha_y.sqrt())
+    }
+
+    // Below this the lobe is narrower than we can sample robustly, so treat it as a mirror
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    pub fn d(&self, wm: &Vec3) -> Float {
+        let cos2 = wm.z * wm.z;
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+        let e = (wm.x * wm.x / (self.alpha_x * self.alpha_x)
+            + wm.y * wm.y / (self.alpha_y * self.alpha_y)) / cos2;
+        let denom = FloatConsts::PI * self.alpha_x * self.alpha_y * cos2 * cos2 * (1.0 + e) * (1.0 + e);
+        Float::from(1.0) / denom
+    }
+
+    pub fn lambda(&self, w: &Vec3) -> Float {
+        let cos2 = w.z * w.z;
+        if cos2 <= 0.0 {
+            return 0.0;
+        }
+        let alpha2_tan2 = (w.x * w.x * self.alpha_x * self.alpha_x
+            + w.y * w.y * self.alpha_y * self.alpha_y) / cos2;
+        0.5 * ((1.0 + alpha2_tan2).sqrt() - 1.0)
+    }
+
+    pub fn g1(&self, w: &Vec3) -> Float {
+        Float::from(1.0) / (1.0 + self.lambda(w))
+    }
+
+    // Height-correlated Smith masking-shadowing
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> Float {
+        Float::from(1.0) / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Density of the normals visible from w
+    pub fn d_visible(&self, w: &Vec3, wm: &Vec3) -> Float {
+        if w.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).abs()
+    }
+
+    // Samples a visible normal (Heitz 2018), always in the upper hemisphere
+    pub fn sample_wm(&self, w: &Vec3) -> Vec3 {
+        let w = if w.z < 0.0 { -*w } else { *w };
+        let wh = Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).normalized();
+
+        let len2 = wh.x * wh.x + wh.y * wh.y;
+        let t1 = if len2 > 0.0 {
+            Vec3::new(-wh.y, wh.x, 0.0) / len2.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = wh.cross(&t1);
+
+        let r = random::<Float>().sqrt();
+        let phi = 2.0 * FloatConsts::PI * random::<Float>();
+        let p1 = r * phi.cos();
+        let p2 = r * phi.sin();
+        let s = 0.5 * (1.0 + wh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * p2;
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * wh;
+
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).normalized()
+    }
+}
+
+pub fn reflect(w: &Vec3, n: &Vec3) -> Vec3 {
+    -*w + 2.0 * w.dot(n) * *n
+}
+
+// Refracts w (pointing away from the surface) through a facet with normal n,
+// eta being the relative IOR on the far side. None on total internal reflection.
+pub fn refract(w: &Vec3, n: &Vec3, eta: Float) -> Option<Vec3> {
+    let (n, eta) = if w.dot(n) < 0.0 { (-*n, 1.0 / eta) } else { (*n, eta) };
+    let cos_i = w.dot(&n);
+    let sin2_i = (1.0 - cos_i * cos_i).max(0.0);
+    let sin2_t = sin2_i / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*w / eta + (cos_i / eta - cos_t) * n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        // Monte Carlo estimate of the integral of D(wm) cos(theta_m) over the hemisphere
+        let ggx = GGX::new(0.5, 0.3);
+        let n = 200000;
+        let mut sum = Float::from(0.0);
+        for _ in 0..n {
+            let wm = Vec3::new_random_on_hemisphere(&Vec3::new(0.0, 0.0, 1.0));
+            sum += ggx.d(&wm) * wm.z * 2.0 * FloatConsts::PI;
+        }
+        assert!((sum / n as Float - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_refract() {
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let w = Vec3::new(0.6, 0.0, 0.8);
+        let t = refract(&w, &n, 1.5).expect("expected refraction");
+        assert!((t.length() - 1.0).abs() < 1e-5);
+        assert!((t.x + 0.6 / 1.5).abs() < 1e-5);
+        assert!(refract(&-Vec3::new(0.9, 0.0, Float::sqrt(0.19)), &n, 1.5).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use crate::{hittable::HitRecord, math::vec3::Vec3, texture::{solid::SolidColor, Texture}, utils::random::random, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, medium::Medium, Material};
+
+// Blends two materials: with probability `weight` a bounce uses `b`, otherwise `a`.
+// The weight can come from a texture (first channel) to mask one material over another.
+#[derive(Clone)]
+pub struct Mix {
+    a: Arc<Box<dyn Material>>,
+    b: Arc<Box<dyn Material>>,
+    weight: Arc<Box<dyn Texture>>,
+}
+
+impl Mix {
+    pub fn new(a: Arc<Box<dyn Material>>, b: Arc<Box<dyn Material>>, weight: Float) -> Self {
+        Self::new_with_texture(a, b, Arc::new(Box::new(SolidColor::new(Vec3::new_diagonal(weight)))))
+    }
+
+    pub fn new_with_texture(a: Arc<Box<dyn Material>>, b: Arc<Box<dyn Material>>, weight: Arc<Box<dyn Texture>>) -> Self {
+        Self { a, b, weight }
+    }
+
+    fn weight(&self, rec: &HitRecord) -> Float {
+        self.weight.value(rec.u, rec.v, &rec.point).x.clamp(0.0, 1.0)
+    }
+}
+
+impl Material for Mix {
+    fn lobes(&self) -> Lobe {
+        self.a.lobes() | self.b.lobes()
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.eval(rec, wo, wi) + w * self.b.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let w = self.weight(rec);
+        let (picked, probability) = if random::<Float>() < w {
+            (&self.b, w)
+        } else {
+            (&self.a, 1.0 - w)
+        };
+        let mut sample = picked.sample(rec, wo)?;
+        if sample.lobe.is_specular() {
+            // picking the lobe with the mixing probability cancels the mixing weight
+            sample.pdf *= probability;
+            return Some(sample);
+        }
+
+        let pdf = self.pdf(rec, wo, &sample.wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let f = self.eval(rec, wo, &sample.wi);
+        sample.weight = f * rec.normal.dot(&sample.wi).abs() / pdf;
+        sample.pdf = pdf;
+        Some(sample)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.pdf(rec, wo, wi) + w * self.b.pdf(rec, wo, wi)
+    }
+
+    fn emitted(&self, rec: &HitRecord, wo: &Vec3) -> Option<Vec3> {
+        let (a, b) = (self.a.emitted(rec, wo), self.b.emitted(rec, wo));
+        if a.is_none() && b.is_none() {
+            return None;
+        }
+        let w = self.weight(rec);
+        Some((1.0 - w) * a.unwrap_or(Vec3::zero()) + w * b.unwrap_or(Vec3::zero()))
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.a.is_emissive() || self.b.is_emissive()
+    }
+
+    // A mix of two media can't be represented, the first one wins
+    fn interior(&self) -> Option<&Medium> {
+        self.a.interior().or_else(|| self.b.interior())
+    }
+
+    // Only a hole in both materials is a hole in the mix
+    fn is_opaque(&self, u: Float, v: Float, point: &Vec3) -> bool {
+        self.a.is_opaque(u, v, point) || self.b.is_opaque(u, v, point)
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Mix {
+            a: Box::new(self.a.describe()?),
+            b: Box::new(self.b.describe()?),
+            weight: self.weight.describe()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::{conductor::{Conductor, ConductorPreset}, dielectric::Dielectric, lambertian::Lambertian, tests::assert_sample_consistent}, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_consistency() {
+        let diffuse: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::new(0.8, 0.2, 0.2))));
+        let glossy: Arc<Box<dyn Material>> = Arc::new(Box::new(Conductor::new_preset(ConductorPreset::Copper, 0.4)));
+        let glass: Arc<Box<dyn Material>> = Arc::new(Box::new(Dielectric::new(Vec3::new_diagonal(1.0), 1.5)));
+        let inner: Arc<Box<dyn Material>> = Arc::new(Box::new(Mix::new(diffuse, glossy, 0.4)));
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(Mix::new(inner, glass, 0.3)));
+
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 0.2), Vec3::new(-0.5, -1.0, -0.2));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        let wo = -ray.direction();
+        assert_sample_consistent(&material, &rec, &wo, 1e-3);
+    }
+}
@@ -1,8 +1,62 @@
 use as_any::AsAny;
 
-use crate::{hittable::HitRecord, math::vec3::Vec3, ray::Ray};
+use crate::{hittable::HitRecord, math::vec3::Vec3, ray::Ray, Float};
 
+use self::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc};
+
+// All directions are in world space and point away from the surface:
+// wo towards the viewer (the negated incoming ray direction), wi towards the light.
 pub trait Material: AsAny {
+    // Lobes this material can produce at all
+    fn lobes(&self) -> Lobe;
+
+    // BSDF value f(wo, wi), without the cosine term.
+    // Always zero for specular lobes.
+    fn eval(&self, _hit_record: &HitRecord, _wo: &Vec3, _wi: &Vec3) -> Vec3 {
+        Vec3::zero()
+    }
+
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3) -> Option<BsdfSample>;
+
+    // Solid angle density with which sample() returns wi.
+    // Always zero for specular lobes.
+    fn pdf(&self, _hit_record: &HitRecord, _wo: &Vec3, _wi: &Vec3) -> Float {
+        0.0
+    }
+
+    // Radiance leaving the surface towards wo
+    fn emitted(&self, _hit_record: &HitRecord, _wo: &Vec3) -> Option<Vec3> {
+        None
+    }
+
+    // Whether emitted() can return anything; geometry made of an emissive
+    // material is sampled directly as a light
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    // Medium filling the object behind this surface, walked by the sampler
+    // while a path is inside
+    fn interior(&self) -> Option<&medium::Medium> {
+        None
+    }
+
+    // Consulted during intersection: surfaces are skipped where this is false
+    fn is_opaque(&self, _u: Float, _v: Float, _point: &Vec3) -> bool {
+        true
+    }
+
+    // Data file form of this material, None when it can't be written out
+    fn describe(&self) -> Option<MaterialDesc> {
+        None
+    }
+}
+
+// Compatibility path for materials that only know how to bounce a ray.
+// They behave as a single specular lobe: never light sampled, never MIS weighted.
+pub trait ScatterMaterial: AsAny {
+    // Only the direction of ray is meaningful: it is made up from wo,
+    // starting a unit away from the hit point
     fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)>;
 
     fn emitted(&self) -> Option<Vec3> {
@@ -10,7 +64,123 @@ pub trait Material: AsAny {
     }
 }
 
+impl<T: ScatterMaterial> Material for T {
+    fn lobes(&self) -> Lobe {
+        Lobe::SPECULAR | Lobe::REFLECTION | Lobe::TRANSMISSION
+    }
+
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let ray = Ray::new(hit_record.point + *wo, -*wo);
+        let (scattered, attenuation) = self.scatter(&ray, hit_record)?;
+        let wi = scattered.direction();
+        let lobe = if wi.dot(&hit_record.normal) >= 0.0 {
+            Lobe::SPECULAR | Lobe::REFLECTION
+        } else {
+            Lobe::SPECULAR | Lobe::TRANSMISSION
+        };
+        Some(BsdfSample::new(wi, attenuation, 1.0, lobe))
+    }
+
+    fn emitted(&self, _hit_record: &HitRecord, _wo: &Vec3) -> Option<Vec3> {
+        ScatterMaterial::emitted(self)
+    }
+
+    fn is_emissive(&self) -> bool {
+        ScatterMaterial::emitted(self).is_some()
+    }
+}
+
+// Same side of the surface test used by every reflection lobe
+pub(crate) fn same_hemisphere(normal: &Vec3, wo: &Vec3, wi: &Vec3) -> bool {
+    normal.dot(wo) * normal.dot(wi) > 0.0
+}
+
+pub mod bsdf;
+pub mod fresnel;
+pub mod microfacet;
 pub mod lambertian;
 pub mod metal;
 pub mod dielectric;
 pub mod light;
+pub mod conductor;
+pub mod roughdielectric;
+pub mod principled;
+pub mod mix;
+pub mod coated;
+pub mod orennayar;
+pub mod cloth;
+pub mod dispersive;
+pub mod normalmap;
+pub mod cutout;
+pub mod medium;
+pub mod subsurface;
+pub mod library;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    // Draws samples from wo and checks each non-specular one against eval() and
+    // pdf() within a relative tolerance. At least half of the draws must give such
+    // a sample. Returns the checked samples for further assertions.
+    pub(crate) fn assert_sample_consistent(material: &Arc<Box<dyn Material>>, rec: &HitRecord, wo: &Vec3, tolerance: Float) -> Vec<BsdfSample> {
+        let draws = 400;
+        let samples: Vec<BsdfSample> = (0..draws)
+            .filter_map(|_| material.sample(rec, wo))
+            .filter(|sample| !sample.lobe.is_specular())
+            .collect();
+        assert!(samples.len() >= draws / 2, "only {} of {} draws gave a sample", samples.len(), draws);
+        for sample in &samples {
+            let f = material.eval(rec, wo, &sample.wi);
+            let pdf = material.pdf(rec, wo, &sample.wi);
+            assert!((pdf - sample.pdf).abs() <= tolerance * pdf, "pdf {} != {}", pdf, sample.pdf);
+            let weight = f * rec.normal.dot(&sample.wi).abs() / pdf;
+            assert!((weight - sample.weight).length() <= tolerance * weight.length(), "weight {:?} != {:?}", weight, sample.weight);
+        }
+        samples
+    }
+
+    // Mirror that tints what it reflects, written the old way
+    struct TintedMirror;
+
+    impl ScatterMaterial for TintedMirror {
+        fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)> {
+            let reflected = ray.direction().reflect(&hit_record.normal);
+            Some((Ray::new(hit_record.point, reflected), Vec3::new(0.9, 0.5, 0.1)))
+        }
+    }
+
+    // Lets rays straight through
+    struct Window;
+
+    impl ScatterMaterial for Window {
+        fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Vec3)> {
+            Some((Ray::new(hit_record.point, ray.direction()), Vec3::new_diagonal(0.8)))
+        }
+    }
+
+    #[test]
+    fn test_scatter_material() {
+        let wo = Vec3::new(1.0, 1.0, 0.0).normalized();
+        let ray = Ray::new(wo, -wo);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        let mirror: Arc<Box<dyn Material>> = Arc::new(Box::new(TintedMirror));
+        let rec = HitRecord::new(&ray, 1.0, normal, (0.0, 0.0), mirror.clone());
+        let sample = mirror.sample(&rec, &wo).expect("expected a sample");
+        assert!((sample.wi - Vec3::new(-1.0, 1.0, 0.0).normalized()).length() < 1e-5, "{:?}", sample.wi);
+        assert!(sample.lobe == Lobe::SPECULAR | Lobe::REFLECTION);
+        assert_eq!(sample.weight, Vec3::new(0.9, 0.5, 0.1));
+        assert_eq!(sample.pdf, 1.0);
+
+        let window: Arc<Box<dyn Material>> = Arc::new(Box::new(Window));
+        let rec = HitRecord::new(&ray, 1.0, normal, (0.0, 0.0), window.clone());
+        let sample = window.sample(&rec, &wo).expect("expected a sample");
+        assert!((sample.wi + wo).length() < 1e-5, "{:?}", sample.wi);
+        assert!(sample.lobe == Lobe::SPECULAR | Lobe::TRANSMISSION);
+        assert_eq!(sample.weight, Vec3::new_diagonal(0.8));
+        assert_eq!(sample.pdf, 1.0);
+    }
+}
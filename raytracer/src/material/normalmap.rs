@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use crate::{hittable::HitRecord, math::vec3::Vec3, texture::Texture, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, medium::Medium, Material};
+
+// Texel offset used for the finite differences of height maps
+const BUMP_DELTA: Float = 1e-3;
+
+// Keeps the shading normal at least this far in front of wo, so the
+// wrapped material never sees the viewer below its hemisphere
+const MIN_COS: Float = 1e-2;
+
+#[derive(Clone)]
+pub enum NormalSource {
+    // Tangent space normals encoded as rgb = (n + 1) / 2; load images with ImageTexture::open_linear
+    TangentSpace(Arc<Box<dyn Texture>>),
+    // Height field (first channel) whose slope tilts the normal
+    Height { height: Arc<Box<dyn Texture>>, scale: Float },
+}
+
+// Wraps a material and replaces the normal it shades with by a perturbed one.
+// The geometric normal still decides which side of the surface a direction is
+// on: samples that would cross it are dropped instead of leaking light.
+#[derive(Clone)]
+pub struct NormalMapped {
+    base: Arc<Box<dyn Material>>,
+    source: NormalSource,
+}
+
+impl NormalMapped {
+    pub fn new_normal_map(base: Arc<Box<dyn Material>>, map: Arc<Box<dyn Texture>>) -> Self {
+        Self { base, source: NormalSource::TangentSpace(map) }
+    }
+
+    pub fn new_bump_map(base: Arc<Box<dyn Material>>, height: Arc<Box<dyn Texture>>, scale: Float) -> Self {
+        Self { base, source: NormalSource::Height { height, scale } }
+    }
+
+    fn perturbed_normal(&self, rec: &HitRecord) -> Vec3 {
+        let frame = rec.shading_frame();
+        let local = match &self.source {
+            NormalSource::TangentSpace(map) => {
+                let c = map.value(rec.u, rec.v, &rec.point);
+                Vec3::new(2.0 * c.x - 1.0, 2.0 * c.y - 1.0, 2.0 * c.z - 1.0)
+            }
+            NormalSource::Height { height, scale } => {
+                let h = height.value(rec.u, rec.v, &rec.point).x;
+                let h_u = height.value(rec.u + BUMP_DELTA, rec.v, &(rec.point + BUMP_DELTA * frame.u)).x;
+                let h_v = height.value(rec.u, rec.v + BUMP_DELTA, &(rec.point + BUMP_DELTA * frame.v)).x;
+                let dh_du = scale * (h_u - h) / BUMP_DELTA;
+                let dh_dv = scale * (h_v - h) / BUMP_DELTA;
+                Vec3::new(-dh_du, -dh_dv, 1.0)
+            }
+        };
+        if local.z <= 0.0 || local.near_zero() {
+            return rec.normal;
+        }
+        frame.to_world(local).normalized()
+    }
+
+    // Copy of the hit seen through the perturbed normal
+    fn shading_record(&self, rec: &HitRecord, wo: &Vec3) -> HitRecord {
+        let mut normal = self.perturbed_normal(rec);
+        let cos = normal.dot(wo);
+        if cos < MIN_COS {
+            normal = (normal + (MIN_COS - cos) * *wo).normalized();
+        }
+        let mut shading = rec.clone();
+        shading.normal = normal;
+        shading
+    }
+
+    // A direction must be on the same side for both normals
+    fn consistent(rec: &HitRecord, shading: &HitRecord, w: &Vec3) -> bool {
+        rec.geometric_normal.dot(w) * shading.normal.dot(w) > 0.0
+    }
+}
+
+impl Material for NormalMapped {
+    fn lobes(&self) -> Lobe {
+        self.base.lobes()
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let shading = self.shading_record(rec, wo);
+        if !Self::consistent(rec, &shading, wi) {
+            return Vec3::zero();
+        }
+        self.base.eval(&shading, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let shading = self.shading_record(rec, wo);
+        let sample = self.base.sample(&shading, wo)?;
+        if !Self::consistent(rec, &shading, &sample.wi) {
+            return None;
+        }
+        Some(sample)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        let shading = self.shading_record(rec, wo);
+        if !Self::consistent(rec, &shading, wi) {
+            return 0.0;
+        }
+        self.base.pdf(&shading, wo, wi)
+    }
+
+    fn emitted(&self, rec: &HitRecord, wo: &Vec3) -> Option<Vec3> {
+        self.base.emitted(rec, wo)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.base.is_emissive()
+    }
+
+    fn interior(&self) -> Option<&Medium> {
+        self.base.interior()
+    }
+
+    fn is_opaque(&self, u: Float, v: Float, point: &Vec3) -> bool {
+        self.base.is_opaque(u, v, point)
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        let base = Box::new(self.base.describe()?);
+        Some(match &self.source {
+            NormalSource::TangentSpace(map) => MaterialDesc::NormalMap { base, map: map.describe()? },
+            NormalSource::Height { height, scale } => MaterialDesc::BumpMap { base, height: height.describe()?, scale: *scale },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::lambertian::Lambertian, ray::Ray, texture::{procedural::NoiseTexture, solid::SolidColor}};
+
+    use super::*;
+
+    #[test]
+    fn test_never_below_surface() {
+        let base: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::new_diagonal(0.8))));
+        // strongly tilted normal map
+        let map: Arc<Box<dyn Texture>> = Arc::new(Box::new(SolidColor::new(Vec3::new(0.95, 0.5, 0.6))));
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(NormalMapped::new_normal_map(base, map)));
+        let ray = Ray::new(Vec3::new(-1.0, 0.3, 0.0), Vec3::new(1.0, -0.3, 0.0));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone())
+            .with_tangent(Vec3::new(1.0, 0.0, 0.0));
+        let wo = -ray.direction();
+        let mut count = 0;
+        for _ in 0..500 {
+            if let Some(sample) = material.sample(&rec, &wo) {
+                assert!(sample.wi.dot(&rec.geometric_normal) > 0.0);
+                count += 1;
+            }
+        }
+        assert!(count > 0);
+        assert_eq!(material.eval(&rec, &wo, &Vec3::new(0.3, -0.5, 0.0).normalized()), Vec3::zero());
+    }
+
+    #[test]
+    fn test_flat_maps_keep_normal() {
+        let base: Arc<Box<dyn Material>> = Arc::new(Box::new(Lambertian::new(Vec3::new_diagonal(0.8))));
+        let flat: Arc<Box<dyn Texture>> = Arc::new(Box::new(SolidColor::new(Vec3::new(0.5, 0.5, 1.0))));
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.2, -1.0, 0.1));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.3, 0.7), base.clone())
+            .with_tangent(Vec3::new(1.0, 0.0, 0.0));
+        let normal_map = NormalMapped::new_normal_map(base.clone(), flat.clone());
+        assert!((normal_map.perturbed_normal(&rec) - rec.normal).length() < 1e-5);
+        let bump_map = NormalMapped::new_bump_map(base.clone(), flat, 1.0);
+        assert!((bump_map.perturbed_normal(&rec) - rec.normal).length() < 1e-5);
+
+        let bumpy: Arc<Box<dyn Texture>> = Arc::new(Box::new(NoiseTexture::new(1, 4.0, 4)));
+        let bump_map = NormalMapped::new_bump_map(base, bumpy, 0.05);
+        let normal = bump_map.perturbed_normal(&rec);
+        assert!((normal.length() - 1.0).abs() < 1e-4);
+        assert!(normal.dot(&rec.normal) > 0.0);
+    }
+}
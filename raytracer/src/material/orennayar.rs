@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use super::{bsdf::{BsdfSample, Lobe}, library::MaterialDesc, same_hemisphere, Material};
+use crate::{hittable::HitRecord, math::{onb::Onb, vec3::Vec3}, texture::{solid::SolidColor, Texture}, Float, FloatConsts};
+
+// Rough diffuse reflection from V-shaped Lambertian microfacets (Oren and Nayar 1994,
+// qualitative model). sigma is the standard deviation of the facet slope angle;
+// at zero it reduces to Lambertian.
+#[derive(Clone)]
+pub struct OrenNayar {
+    albedo: Arc<Box<dyn Texture>>,
+    sigma: Float,
+    a: Float,
+    b: Float,
+}
+
+impl OrenNayar {
+    // sigma in degrees
+    pub fn new(albedo: Vec3, sigma: Float) -> OrenNayar {
+        OrenNayar::new_with_texture(Arc::new(Box::new(SolidColor::new(albedo))), sigma)
+    }
+
+    pub fn new_with_texture(albedo: Arc<Box<dyn Texture>>, sigma: Float) -> OrenNayar {
+        let sigma2 = sigma.to_radians().powi(2);
+        OrenNayar {
+            albedo,
+            sigma,
+            a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+
+    // Directions in the frame around the normal facing wo
+    fn reflectance(&self, wo: &Vec3, wi: &Vec3) -> Float {
+        let sin_o = (1.0 - wo.z * wo.z).max(0.0).sqrt();
+        let sin_i = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+        let max_cos = if sin_o > 1e-4 && sin_i > 1e-4 {
+            ((wo.x * wi.x + wo.y * wi.y) / (sin_o * sin_i)).max(0.0)
+        } else {
+            0.0
+        };
+        let (sin_alpha, tan_beta) = if wi.z.abs() > wo.z.abs() {
+            (sin_o, sin_i / wi.z.abs())
+        } else {
+            (sin_i, sin_o / wo.z.abs())
+        };
+        self.a + self.b * max_cos * sin_alpha * tan_beta
+    }
+}
+
+impl Material for OrenNayar {
+    fn lobes(&self) -> Lobe {
+        Lobe::DIFFUSE | Lobe::REFLECTION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if !same_hemisphere(&rec.normal, wo, wi) {
+            return Vec3::zero();
+        }
+        let onb = Onb::new(rec.normal);
+        let r = self.reflectance(&onb.to_local(*wo), &onb.to_local(*wi));
+        self.albedo.value(rec.u, rec.v, &rec.point) * r / FloatConsts::PI
+    }
+
+    // The rough lobe stays close to cosine shaped, so cosine sampling keeps variance low
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let onb = Onb::new(rec.normal);
+        let local = Vec3::new_random_cosine_direction();
+        if local.z <= 0.0 {
+            return None;
+        }
+        let r = self.reflectance(&onb.to_local(*wo), &local);
+        Some(BsdfSample::new(
+            onb.to_world(local),
+            self.albedo.value(rec.u, rec.v, &rec.point) * r,
+            local.z / FloatConsts::PI,
+            Lobe::DIFFUSE | Lobe::REFLECTION,
+        ))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        if !same_hemisphere(&rec.normal, wo, wi) {
+            return 0.0;
+        }
+        rec.normal.dot(wi).abs() / FloatConsts::PI
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::OrenNayar { albedo: self.albedo.describe()?, sigma: self.sigma })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::tests::assert_sample_consistent, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_consistency() {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(OrenNayar::new(Vec3::new(0.5, 0.25, 1.0), 30.0)));
+        let ray = Ray::new(Vec3::new(0.3, 1.0, 0.2), Vec3::new(-0.3, -1.0, -0.2));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        let wo = -ray.direction();
+        assert_sample_consistent(&material, &rec, &wo, 1e-4);
+    }
+
+    #[test]
+    fn test_zero_sigma_is_lambertian() {
+        let material = OrenNayar::new(Vec3::new_diagonal(1.0), 0.0);
+        let wo = Vec3::new(0.3, 0.2, 0.9).normalized();
+        let wi = Vec3::new(-0.6, 0.1, 0.4).normalized();
+        assert!((material.reflectance(&wo, &wi) - 1.0).abs() < 1e-6);
+    }
+}
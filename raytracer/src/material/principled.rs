@@ -0,0 +1,370 @@
+use std::sync::Arc;
+
+use crate::{hittable::HitRecord, math::vec3::Vec3, texture::{solid::SolidColor, Texture}, utils::random::random, Float, FloatConsts};
+
+use super::{bsdf::{BsdfSample, Lobe}, fresnel, library::{MaterialDesc, PrincipledDesc}, microfacet::{reflect, GGX}, roughdielectric::RoughDielectric, Material};
+
+// Roughness is clamped so no lobe degenerates into a delta distribution
+const MIN_ROUGHNESS: Float = 0.05;
+const CLEARCOAT_F0: Float = 0.04;
+
+fn constant(value: Float) -> Arc<Box<dyn Texture>> {
+    Arc::new(Box::new(SolidColor::new(Vec3::new_diagonal(value))))
+}
+
+fn luminance(color: &Vec3) -> Float {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+fn mix(a: Vec3, b: Vec3, t: Float) -> Vec3 {
+    (1.0 - t) * a + t * b
+}
+
+// Parameters resolved at one shading point
+struct Params {
+    base_color: Vec3,
+    metallic: Float,
+    roughness: Float,
+    specular: Float,
+    sheen: Float,
+    sheen_tint: Float,
+    clearcoat: Float,
+    clearcoat_roughness: Float,
+    transmission: Float,
+}
+
+impl Params {
+    fn diffuse_weight(&self) -> Float {
+        (1.0 - self.metallic) * (1.0 - self.transmission)
+    }
+
+    fn glass_weight(&self) -> Float {
+        (1.0 - self.metallic) * self.transmission
+    }
+
+    fn specular_weight(&self) -> Float {
+        1.0 - self.glass_weight()
+    }
+
+    fn specular_f0(&self) -> Vec3 {
+        mix(Vec3::new_diagonal(0.08 * self.specular), self.base_color, self.metallic)
+    }
+
+    fn specular_distribution(&self) -> GGX {
+        GGX::from_roughness(self.roughness, self.roughness)
+    }
+
+    fn clearcoat_distribution(&self) -> GGX {
+        GGX::from_roughness(self.clearcoat_roughness, self.clearcoat_roughness)
+    }
+
+    // Probabilities of sampling the diffuse, specular, clearcoat and glass lobes
+    fn lobe_probabilities(&self) -> [Float; 4] {
+        let weights = [
+            self.diffuse_weight(),
+            self.specular_weight(),
+            0.25 * self.clearcoat,
+            self.glass_weight(),
+        ];
+        let total: Float = weights.iter().sum();
+        weights.map(|w| w / total)
+    }
+}
+
+// Disney style "principled" material: one parameter set that blends a Burley
+// diffuse with sheen, a GGX specular layer, a clearcoat and rough transmission.
+// Every parameter can be driven by a texture; scalar ones read its first channel.
+#[derive(Clone)]
+pub struct Principled {
+    base_color: Arc<Box<dyn Texture>>,
+    metallic: Arc<Box<dyn Texture>>,
+    roughness: Arc<Box<dyn Texture>>,
+    specular: Arc<Box<dyn Texture>>,
+    sheen: Arc<Box<dyn Texture>>,
+    sheen_tint: Arc<Box<dyn Texture>>,
+    clearcoat: Arc<Box<dyn Texture>>,
+    clearcoat_roughness: Arc<Box<dyn Texture>>,
+    transmission: Arc<Box<dyn Texture>>,
+    refraction_index: Float,
+}
+
+impl Principled {
+    pub fn new(base_color: Vec3) -> Self {
+        Self::new_with_texture(Arc::new(Box::new(SolidColor::new(base_color))))
+    }
+
+    pub fn new_with_texture(base_color: Arc<Box<dyn Texture>>) -> Self {
+        Self {
+            base_color,
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            sheen: constant(0.0),
+            sheen_tint: constant(0.5),
+            clearcoat: constant(0.0),
+            clearcoat_roughness: constant(0.1),
+            transmission: constant(0.0),
+            refraction_index: 1.5,
+        }
+    }
+
+    pub fn with_metallic(self, metallic: Float) -> Self {
+        self.with_metallic_texture(constant(metallic))
+    }
+
+    pub fn with_metallic_texture(mut self, metallic: Arc<Box<dyn Texture>>) -> Self {
+        self.metallic = metallic;
+        self
+    }
+
+    pub fn with_roughness(self, roughness: Float) -> Self {
+        self.with_roughness_texture(constant(roughness))
+    }
+
+    pub fn with_roughness_texture(mut self, roughness: Arc<Box<dyn Texture>>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    // 0.5 gives the 4% reflectance of common dielectrics
+    pub fn with_specular(self, specular: Float) -> Self {
+        self.with_specular_texture(constant(specular))
+    }
+
+    pub fn with_specular_texture(mut self, specular: Arc<Box<dyn Texture>>) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    pub fn with_sheen(self, sheen: Float, sheen_tint: Float) -> Self {
+        self.with_sheen_texture(constant(sheen), constant(sheen_tint))
+    }
+
+    pub fn with_sheen_texture(mut self, sheen: Arc<Box<dyn Texture>>, sheen_tint: Arc<Box<dyn Texture>>) -> Self {
+        self.sheen = sheen;
+        self.sheen_tint = sheen_tint;
+        self
+    }
+
+    pub fn with_clearcoat(self, clearcoat: Float, clearcoat_roughness: Float) -> Self {
+        self.with_clearcoat_texture(constant(clearcoat), constant(clearcoat_roughness))
+    }
+
+    pub fn with_clearcoat_texture(mut self, clearcoat: Arc<Box<dyn Texture>>, clearcoat_roughness: Arc<Box<dyn Texture>>) -> Self {
+        self.clearcoat = clearcoat;
+        self.clearcoat_roughness = clearcoat_roughness;
+        self
+    }
+
+    pub fn with_transmission(self, transmission: Float, refraction_index: Float) -> Self {
+        self.with_transmission_texture(constant(transmission), refraction_index)
+    }
+
+    pub fn with_transmission_texture(mut self, transmission: Arc<Box<dyn Texture>>, refraction_index: Float) -> Self {
+        self.transmission = transmission;
+        self.refraction_index = refraction_index;
+        self
+    }
+
+    fn params(&self, rec: &HitRecord) -> Params {
+        let scalar = |texture: &Arc<Box<dyn Texture>>| texture.value(rec.u, rec.v, &rec.point).x.clamp(0.0, 1.0);
+        Params {
+            base_color: self.base_color.value(rec.u, rec.v, &rec.point),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness).max(MIN_ROUGHNESS),
+            specular: scalar(&self.specular),
+            sheen: scalar(&self.sheen),
+            sheen_tint: scalar(&self.sheen_tint),
+            clearcoat: scalar(&self.clearcoat),
+            clearcoat_roughness: scalar(&self.clearcoat_roughness).max(MIN_ROUGHNESS),
+            transmission: scalar(&self.transmission),
+        }
+    }
+
+    fn glass(&self, params: &Params) -> RoughDielectric {
+        RoughDielectric::new(self.refraction_index, params.roughness)
+    }
+
+    // Sum of the reflection lobes, in the shading frame
+    fn eval_reflection(params: &Params, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vec3::zero();
+        }
+        let wm = (*wo + *wi).normalized();
+        let cos_d = wi.dot(&wm);
+        let schlick_weight = |cos: Float| (1.0 - cos.clamp(0.0, 1.0)).powi(5);
+
+        // Burley diffuse with retro-reflection at grazing angles
+        let fd90 = 0.5 + 2.0 * params.roughness * cos_d * cos_d;
+        let fl = schlick_weight(wi.z);
+        let fv = schlick_weight(wo.z);
+        let diffuse = params.base_color / FloatConsts::PI
+            * (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+
+        let lum = luminance(&params.base_color);
+        let tint = if lum > 0.0 { params.base_color / lum } else { Vec3::new_diagonal(1.0) };
+        let sheen = params.sheen * schlick_weight(cos_d) * mix(Vec3::new_diagonal(1.0), tint, params.sheen_tint);
+
+        let specular_distribution = params.specular_distribution();
+        let specular = fresnel::schlick(wo.dot(&wm), params.specular_f0())
+            * (specular_distribution.d(&wm) * specular_distribution.g(wo, wi) / (4.0 * wo.z * wi.z));
+
+        let clearcoat_distribution = params.clearcoat_distribution();
+        let clearcoat = fresnel::schlick(wo.dot(&wm), Vec3::new_diagonal(CLEARCOAT_F0))
+            * (0.25 * params.clearcoat * clearcoat_distribution.d(&wm) * clearcoat_distribution.g(wo, wi)
+                / (4.0 * wo.z * wi.z));
+
+        params.diffuse_weight() * diffuse
+            + params.diffuse_weight() * sheen
+            + params.specular_weight() * specular
+            + clearcoat
+    }
+
+    fn pdf_reflection(params: &Params, probabilities: &[Float; 4], wo: &Vec3, wi: &Vec3) -> Float {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let wm = (*wo + *wi).normalized();
+        let jacobian = 1.0 / (4.0 * wo.dot(&wm).abs());
+        probabilities[0] * wi.z / FloatConsts::PI
+            + probabilities[1] * params.specular_distribution().d_visible(wo, &wm) * jacobian
+            + probabilities[2] * params.clearcoat_distribution().d_visible(wo, &wm) * jacobian
+    }
+}
+
+impl Material for Principled {
+    fn lobes(&self) -> Lobe {
+        Lobe::DIFFUSE | Lobe::GLOSSY | Lobe::REFLECTION | Lobe::TRANSMISSION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let params = self.params(rec);
+        let frame = rec.shading_frame();
+        let mut f = Self::eval_reflection(&params, &frame.to_local(*wo), &frame.to_local(*wi));
+        if params.glass_weight() > 0.0 {
+            let mut glass = self.glass(&params).eval(rec, wo, wi);
+            if rec.normal.dot(wi) < 0.0 {
+                glass *= params.base_color;
+            }
+            f += params.glass_weight() * glass;
+        }
+        f
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let params = self.params(rec);
+        let probabilities = params.lobe_probabilities();
+        let frame = rec.shading_frame();
+        let wo_local = frame.to_local(*wo);
+
+        let u = random::<Float>();
+        let mut eta = 1.0;
+        let wi = if u < probabilities[0] {
+            let local = Vec3::new_random_cosine_direction();
+            frame.to_world(local)
+        } else if u < probabilities[0] + probabilities[1] + probabilities[2] {
+            if wo_local.z <= 0.0 {
+                return None;
+            }
+            let distribution = if u < probabilities[0] + probabilities[1] {
+                params.specular_distribution()
+            } else {
+                params.clearcoat_distribution()
+            };
+            let wm = distribution.sample_wm(&wo_local);
+            frame.to_world(reflect(&wo_local, &wm))
+        } else {
+            let sample = self.glass(&params).sample(rec, wo)?;
+            eta = sample.eta;
+            sample.wi
+        };
+
+        let pdf = self.pdf(rec, wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let f = self.eval(rec, wo, &wi);
+        let lobe = if rec.normal.dot(&wi) < 0.0 {
+            Lobe::GLOSSY | Lobe::TRANSMISSION
+        } else if u < probabilities[0] {
+            Lobe::DIFFUSE | Lobe::REFLECTION
+        } else {
+            Lobe::GLOSSY | Lobe::REFLECTION
+        };
+        Some(BsdfSample::new(wi, f * rec.normal.dot(&wi).abs() / pdf, pdf, lobe).with_eta(eta))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        let params = self.params(rec);
+        let probabilities = params.lobe_probabilities();
+        let frame = rec.shading_frame();
+        let mut pdf = Self::pdf_reflection(&params, &probabilities, &frame.to_local(*wo), &frame.to_local(*wi));
+        if probabilities[3] > 0.0 {
+            pdf += probabilities[3] * self.glass(&params).pdf(rec, wo, wi);
+        }
+        pdf
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Principled(Box::new(PrincipledDesc {
+            base_color: self.base_color.describe()?,
+            metallic: self.metallic.describe()?,
+            roughness: self.roughness.describe()?,
+            specular: self.specular.describe()?,
+            sheen: self.sheen.describe()?,
+            sheen_tint: self.sheen_tint.describe()?,
+            clearcoat: self.clearcoat.describe()?,
+            clearcoat_roughness: self.clearcoat_roughness.describe()?,
+            transmission: self.transmission.describe()?,
+            refraction_index: self.refraction_index,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::tests::assert_sample_consistent, ray::Ray, texture::checker::{CheckerMode, CheckerTexture}};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_consistency() {
+        let roughness: Arc<Box<dyn Texture>> = Arc::new(Box::new(CheckerTexture::new_with_colors(
+            CheckerMode::Uv, 2.0, Vec3::new_diagonal(0.2), Vec3::new_diagonal(0.7),
+        )));
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            Principled::new(Vec3::new(0.8, 0.3, 0.2))
+                .with_metallic(0.3)
+                .with_roughness_texture(roughness)
+                .with_sheen(0.5, 0.5)
+                .with_clearcoat(1.0, 0.1)
+                .with_transmission(0.5, 1.45)
+        ));
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 0.2), Vec3::new(-0.5, -1.0, -0.2));
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.3, 0.6), material.clone())
+            .with_tangent(Vec3::new(1.0, 0.0, 0.0));
+        let wo = -ray.direction();
+        let lobes = assert_sample_consistent(&material, &rec, &wo, 1e-3)
+            .iter()
+            .fold(Lobe::NONE, |lobes, sample| lobes | sample.lobe);
+        assert!(lobes.contains(Lobe::DIFFUSE | Lobe::GLOSSY | Lobe::REFLECTION | Lobe::TRANSMISSION));
+    }
+
+    #[test]
+    fn test_no_sheen_on_glass() {
+        // sheen sits on the diffuse base, which full transmission replaces
+        let glass: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            Principled::new(Vec3::new_diagonal(1.0)).with_transmission(1.0, 1.5)
+        ));
+        let sheen_glass: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            Principled::new(Vec3::new_diagonal(1.0)).with_sheen(1.0, 0.0).with_transmission(1.0, 1.5)
+        ));
+        let ray = Ray::new(Vec3::new(2.0, 0.3, 0.0), Vec3::new(-2.0, -0.3, 0.0));
+        let wo = -ray.direction().normalized();
+        let wi = Vec3::new(-0.2, 1.0, 0.3).normalized();
+        let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), glass.clone());
+        let expected = glass.eval(&rec, &wo, &wi);
+        let actual = sheen_glass.eval(&rec, &wo, &wi);
+        assert!((actual - expected).length() <= 1e-6 * expected.length(), "{:?} != {:?}", actual, expected);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{hittable::HitRecord, math::{onb::Onb, vec3::Vec3}, utils::random::random, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, fresnel, library::{to_array, MaterialDesc, Roughness}, microfacet::{reflect, refract, GGX}, Material};
+
+// Microfacet reflection and transmission through a GGX rough interface
+// (Walter et al. 2007) with exact dielectric Fresnel.
+// Light travelling inside is absorbed following Beer-Lambert, so tinted
+// glass gets darker the thicker it is instead of losing a fixed amount per bounce.
+#[derive(Clone)]
+pub struct RoughDielectric {
+    refraction_index: Float,
+    distribution: GGX,
+    absorption: Vec3,
+}
+
+impl RoughDielectric {
+    pub fn new(refraction_index: Float, roughness: Float) -> Self {
+        Self::new_anisotropic(refraction_index, roughness, roughness)
+    }
+
+    pub fn new_anisotropic(refraction_index: Float, roughness_u: Float, roughness_v: Float) -> Self {
+        Self {
+            refraction_index,
+            distribution: GGX::from_roughness(roughness_u, roughness_v),
+            absorption: Vec3::zero(),
+        }
+    }
+
+    // Absorption coefficient per unit distance, for each channel
+    pub fn with_absorption(mut self, absorption: Vec3) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    // Absorption such that light keeps `color` after travelling `distance` inside
+    pub fn with_transmittance(self, color: Vec3, distance: Float) -> Self {
+        let absorption = Vec3::new(
+            -color.x.max(1e-6).ln() / distance,
+            -color.y.max(1e-6).ln() / distance,
+            -color.z.max(1e-6).ln() / distance,
+        );
+        self.with_absorption(absorption)
+    }
+
+    // A ray hitting the back face has just travelled rec.t through the interior
+    fn transmittance(&self, rec: &HitRecord) -> Vec3 {
+        if rec.front_face {
+            return Vec3::new_diagonal(1.0);
+        }
+        let tau = self.absorption * rec.t;
+        Vec3::new((-tau.x).exp(), (-tau.y).exp(), (-tau.z).exp())
+    }
+
+    // Frame around the outward normal: local z > 0 is outside the object
+    fn frame(rec: &HitRecord) -> Onb {
+        let outward_normal = if rec.front_face { rec.normal } else { -rec.normal };
+        Onb::new_with_tangent(outward_normal, rec.tangent)
+    }
+
+    // Half vector for the given pair together with the relative IOR for transmission
+    fn half_vector(&self, wo: &Vec3, wi: &Vec3) -> Option<(Vec3, Float)> {
+        let reflect = wo.z * wi.z > 0.0;
+        let etap = if reflect {
+            1.0
+        } else if wo.z > 0.0 {
+            self.refraction_index
+        } else {
+            1.0 / self.refraction_index
+        };
+        let wm = *wi * etap + *wo;
+        if wo.z == 0.0 || wi.z == 0.0 || wm.near_zero() {
+            return None;
+        }
+        let wm = wm.normalized();
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+        // discard back facing microfacets
+        if wm.dot(wi) * wi.z < 0.0 || wm.dot(wo) * wo.z < 0.0 {
+            return None;
+        }
+        Some((wm, etap))
+    }
+
+    fn eval_local(&self, wo: &Vec3, wi: &Vec3) -> (Vec3, Float) {
+        let Some((wm, etap)) = self.half_vector(wo, wi) else {
+            return (Vec3::zero(), 0.0);
+        };
+        let r = fresnel::dielectric(wo.dot(&wm), self.refraction_index);
+        let t = 1.0 - r;
+        let d = self.distribution.d(&wm);
+        let g = self.distribution.g(wo, wi);
+        let d_visible = self.distribution.d_visible(wo, &wm);
+
+        if wo.z * wi.z > 0.0 {
+            let f = d * g * r / (4.0 * wi.z * wo.z).abs();
+            let pdf = d_visible / (4.0 * wo.dot(&wm).abs()) * r;
+            (Vec3::new_diagonal(f), pdf)
+        } else {
+            let denom = wi.dot(&wm) + wo.dot(&wm) / etap;
+            let denom = denom * denom;
+            let f = t * d * g * (wi.dot(&wm) * wo.dot(&wm) / (wi.z * wo.z * denom)).abs();
+            // radiance is compressed into a smaller solid angle when entering a denser medium
+            let f = f / (etap * etap);
+            let pdf = d_visible * wi.dot(&wm).abs() / denom * t;
+            (Vec3::new_diagonal(f), pdf)
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn lobes(&self) -> Lobe {
+        if self.distribution.effectively_smooth() {
+            Lobe::SPECULAR | Lobe::REFLECTION | Lobe::TRANSMISSION
+        } else {
+            Lobe::GLOSSY | Lobe::REFLECTION | Lobe::TRANSMISSION
+        }
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if self.distribution.effectively_smooth() {
+            return Vec3::zero();
+        }
+        let frame = Self::frame(rec);
+        let (f, _) = self.eval_local(&frame.to_local(*wo), &frame.to_local(*wi));
+        f * self.transmittance(rec)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        let frame = Self::frame(rec);
+        let wo_local = frame.to_local(*wo);
+        if wo_local.z == 0.0 {
+            return None;
+        }
+        let transmittance = self.transmittance(rec);
+
+        if self.distribution.effectively_smooth() {
+            let n = Vec3::new(0.0, 0.0, 1.0);
+            let r = fresnel::dielectric(wo_local.z, self.refraction_index);
+            if random::<Float>() < r {
+                let wi = reflect(&wo_local, &n);
+                return Some(BsdfSample::new(
+                    frame.to_world(wi),
+                    transmittance,
+                    r,
+                    Lobe::SPECULAR | Lobe::REFLECTION,
+                ));
+            }
+            let wi = refract(&wo_local, &n, self.refraction_index)?;
+            let etap = if wo_local.z > 0.0 { self.refraction_index } else { 1.0 / self.refraction_index };
+            return Some(BsdfSample::new(
+                frame.to_world(wi),
+                transmittance / (etap * etap),
+                1.0 - r,
+                Lobe::SPECULAR | Lobe::TRANSMISSION,
+            ).with_eta(etap));
+        }
+
+        let wm = self.distribution.sample_wm(&wo_local);
+        let r = fresnel::dielectric(wo_local.dot(&wm), self.refraction_index);
+        let (wi, lobe, eta) = if random::<Float>() < r {
+            let wi = reflect(&wo_local, &wm);
+            if wi.z * wo_local.z <= 0.0 {
+                return None;
+            }
+            (wi, Lobe::GLOSSY | Lobe::REFLECTION, 1.0)
+        } else {
+            let wi = refract(&wo_local, &wm, self.refraction_index)?;
+            if wi.z * wo_local.z >= 0.0 {
+                return None;
+            }
+            let etap = if wo_local.z > 0.0 { self.refraction_index } else { 1.0 / self.refraction_index };
+            (wi, Lobe::GLOSSY | Lobe::TRANSMISSION, etap)
+        };
+
+        let (f, pdf) = self.eval_local(&wo_local, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample::new(
+            frame.to_world(wi),
+            f * transmittance * wi.z.abs() / pdf,
+            pdf,
+            lobe,
+        ).with_eta(eta))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        if self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+        let frame = Self::frame(rec);
+        let (_, pdf) = self.eval_local(&frame.to_local(*wo), &frame.to_local(*wi));
+        pdf
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        let (u, v) = self.distribution.roughness();
+        Some(MaterialDesc::RoughDielectric {
+            refraction_index: self.refraction_index,
+            roughness: Roughness::from_pair(u, v),
+            absorption: to_array(self.absorption),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{material::tests::assert_sample_consistent, ray::Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_consistency() {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(RoughDielectric::new(1.5, 0.4)));
+        for direction in [Vec3::new(-0.5, -1.0, -0.2), Vec3::new(-0.5, 1.0, -0.2)] {
+            let ray = Ray::new(Vec3::new(0.5, 1.0, 0.2), direction);
+            let rec = HitRecord::new(&ray, 1.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone())
+                .with_tangent(Vec3::new(1.0, 0.0, 0.0));
+            let wo = -ray.direction();
+            let lobes = assert_sample_consistent(&material, &rec, &wo, 1e-3)
+                .iter()
+                .fold(Lobe::NONE, |lobes, sample| lobes | sample.lobe);
+            assert!(lobes.contains(Lobe::REFLECTION | Lobe::TRANSMISSION));
+        }
+    }
+
+    #[test]
+    fn test_absorption() {
+        let material: Arc<Box<dyn Material>> = Arc::new(Box::new(
+            RoughDielectric::new(1.5, 0.0).with_transmittance(Vec3::new(0.5, 1.0, 1.0), 1.0)
+        ));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let rec = HitRecord::new(&ray, 2.0, Vec3::new(0.0, 1.0, 0.0), (0.0, 0.0), material.clone());
+        assert!(!rec.front_face);
+        let sample = material.sample(&rec, &-ray.direction()).expect("expected a sample");
+        assert!((sample.weight.x / sample.weight.y - 0.25).abs() < 1e-4);
+    }
+}
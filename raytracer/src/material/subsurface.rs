@@ -0,0 +1,62 @@
+use crate::{hittable::HitRecord, math::vec3::Vec3, Float};
+
+use super::{bsdf::{BsdfSample, Lobe}, library::{to_array, MaterialDesc, Roughness}, medium::Medium, roughdielectric::RoughDielectric, Material};
+
+// Skin, wax, marble, milk: a dielectric boundary around a scattering medium.
+// Light refracting in is random walked by the sampler until it reaches the
+// boundary again, so it leaves somewhere else than where it entered.
+#[derive(Clone)]
+pub struct Subsurface {
+    boundary: RoughDielectric,
+    medium: Medium,
+}
+
+impl Subsurface {
+    pub fn new(mean_free_path: Vec3, albedo: Vec3, anisotropy: Float) -> Self {
+        Self {
+            boundary: RoughDielectric::new(1.4, 0.0),
+            medium: Medium::new(mean_free_path, albedo, anisotropy),
+        }
+    }
+
+    pub fn with_boundary(mut self, refraction_index: Float, roughness: Float) -> Self {
+        self.boundary = RoughDielectric::new(refraction_index, roughness);
+        self
+    }
+}
+
+impl Material for Subsurface {
+    fn lobes(&self) -> Lobe {
+        self.boundary.lobes()
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        self.boundary.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+        self.boundary.sample(rec, wo)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+        self.boundary.pdf(rec, wo, wi)
+    }
+
+    fn interior(&self) -> Option<&Medium> {
+        Some(&self.medium)
+    }
+
+    fn describe(&self) -> Option<MaterialDesc> {
+        let MaterialDesc::RoughDielectric { refraction_index, roughness: Roughness::Isotropic(roughness), .. } = self.boundary.describe()? else {
+            return None;
+        };
+        let (mean_free_path, albedo, anisotropy) = self.medium.parameters();
+        Some(MaterialDesc::Subsurface {
+            mean_free_path: to_array(mean_free_path),
+            albedo: to_array(albedo),
+            anisotropy,
+            refraction_index,
+            roughness,
+        })
+    }
+}
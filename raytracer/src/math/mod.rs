@@ -1,3 +1,4 @@
 pub mod vec3;
 pub mod vec3extend;
-pub mod transform;
\ No newline at end of file
+pub mod transform;
+pub mod onb;
\ No newline at end of file
@@ -0,0 +1,68 @@
+use crate::Float;
+
+use super::vec3::Vec3;
+
+// Orthonormal basis with w along the given normal,
+// used to move directions between world space and a z-up local frame.
+#[derive(Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(normal: Vec3) -> Self {
+        let w = normal.normalized();
+        // Duff et al., "Building an Orthonormal Basis, Revisited"
+        let sign = Float::from(1.0).copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        let u = Vec3::new(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x);
+        let v = Vec3::new(b, sign + w.y * w.y * a, -w.y);
+        Self { u, v, w }
+    }
+
+    pub fn new_with_tangent(normal: Vec3, tangent: Vec3) -> Self {
+        let w = normal.normalized();
+        let u = tangent - tangent.dot(&w) * w;
+        if u.near_zero() {
+            return Self::new(w);
+        }
+        let u = u.normalized();
+        let v = w.cross(&u);
+        Self { u, v, w }
+    }
+
+    pub fn to_world(&self, local: Vec3) -> Vec3 {
+        local.x * self.u + local.y * self.v + local.z * self.w
+    }
+
+    pub fn to_local(&self, world: Vec3) -> Vec3 {
+        Vec3::new(world.dot(&self.u), world.dot(&self.v), world.dot(&self.w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for normal in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, -3.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ] {
+            let onb = Onb::new(normal);
+            assert!((onb.u.dot(&onb.v)).abs() < 1e-5);
+            assert!((onb.u.dot(&onb.w)).abs() < 1e-5);
+            assert!((onb.u.cross(&onb.v) - onb.w).length() < 1e-5);
+            assert!((onb.to_world(Vec3::new(0.0, 0.0, 1.0)) - normal.normalized()).length() < 1e-5);
+
+            let v = Vec3::new(0.3, -0.2, 0.9);
+            assert!((onb.to_world(onb.to_local(v)) - v).length() < 1e-5);
+        }
+    }
+}
@@ -52,6 +52,23 @@ impl Vec3 {
         }
     }
 
+    // Cosine weighted direction around +z, pdf = cos(theta) / pi
+    pub fn new_random_cosine_direction() -> Self {
+        let r1: Float = random();
+        let r2: Float = random();
+        let phi = 2.0 * std::f32::consts::PI * r1;
+        let r = r2.sqrt();
+        Vec3::new(r * phi.cos(), r * phi.sin(), (Float::from(1.0) - r2).max(0.0).sqrt())
+    }
+
+    // Uniform direction around +z with 1 - cos(theta) below extent, pdf = 1 / (2 pi extent)
+    pub fn new_random_in_cone(extent: Float) -> Self {
+        let z = 1.0 - random::<Float>() * extent;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * std::f32::consts::PI * random::<Float>();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
     pub fn new_random() -> Self {
         Self::new_random_range(Float::from(0.0)..Float::from(1.0))
     }
@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
-use flume::Receiver;
+use flume::{bounded, Receiver};
 use indicatif::ProgressBar;
 use tokio::task::JoinHandle;
 
-use crate::{math::vec3::Vec3, utils::image::Image, Float};
+use crate::{math::vec3::Vec3, utils::image::{Color, Image}, Float};
 
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -19,6 +19,7 @@ pub struct Imager {
     height: usize,
     samples_per_pixel: usize,
     progressbar: Option<Box<ProgressBar>>,
+    heatmap: bool,
 }
 
 impl Imager {
@@ -28,10 +29,24 @@ impl Imager {
         samples_per_pixel: usize,
         progressbar: Option<Box<ProgressBar>>
     ) -> Self {
-        Self { width, height, samples_per_pixel, progressbar }
+        Self { width, height, samples_per_pixel, progressbar, heatmap: false }
+    }
+
+    // Treat the red channel of incoming samples as a cost and map the
+    // per-pixel average to a false-color ramp instead of a gamma-corrected color.
+    pub(super) fn with_heatmap(mut self) -> Self {
+        self.heatmap = true;
+        self
     }
 
     pub async fn collect(&self, in_channel: Receiver<SampledColor>) -> Image {
+        let (_, no_splats) = bounded(1);
+        self.collect_with_splats(in_channel, no_splats).await
+    }
+
+    // Light tracing adds splats to arbitrary pixels on top of the samples of each
+    // pixel; they are scaled like samples but don't count towards a pixel's total
+    pub async fn collect_with_splats(&self, in_channel: Receiver<SampledColor>, splat_channel: Receiver<SampledColor>) -> Image {
         let color_multiplier = Float::from(1.0) / self.samples_per_pixel as Float;
 
         let mut image = Image::new_with_gamma_correction(
@@ -43,27 +58,51 @@ impl Imager {
         let mut pixels = vec![Vec3::zero(); self.width*self.height];
         let mut num_acc_cnt = vec![0usize; self.width*self.height];
 
-        while let Ok(sampled_color) = in_channel.recv_async().await {
-            let x = sampled_color.x as usize;
-            let y = sampled_color.y as usize;
-            let idx = y*self.width + x;
-            pixels[idx] += sampled_color.color * color_multiplier;
-            num_acc_cnt[idx] += 1;
-            if num_acc_cnt[idx] == self.samples_per_pixel {
-                image.set_pixel(x, y, pixels[idx].into());
-                if let Some(progressbar) = self.progressbar.clone() {
-                    progressbar.inc(1);
+        loop {
+            tokio::select! {
+                Ok(sampled_color) = in_channel.recv_async() => {
+                    let idx = sampled_color.y as usize*self.width + sampled_color.x as usize;
+                    pixels[idx] += sampled_color.color * color_multiplier;
+                    num_acc_cnt[idx] += 1;
+                    if num_acc_cnt[idx] == self.samples_per_pixel {
+                        if let Some(progressbar) = self.progressbar.clone() {
+                            progressbar.inc(1);
+                        }
+                    }
                 }
+                Ok(splat) = splat_channel.recv_async() => {
+                    let idx = splat.y as usize*self.width + splat.x as usize;
+                    pixels[idx] += splat.color * color_multiplier;
+                }
+                else => break,
+            }
+        }
+
+        if self.heatmap {
+            return self.heatmap_image(&pixels);
+        }
+        for (idx, pixel) in pixels.iter().enumerate() {
+            if num_acc_cnt[idx] == self.samples_per_pixel {
+                image.set_pixel(idx % self.width, idx / self.width, (*pixel).into());
             }
         }
         image
     }
+
+    fn heatmap_image(&self, pixels: &[Vec3]) -> Image {
+        let max_cost = pixels.iter().fold(Float::from(0.0), |acc, p| acc.max(p.x));
+        let mut image = Image::new(self.width, self.height);
+        for (idx, pixel) in pixels.iter().enumerate() {
+            let t = if max_cost > 0.0 { pixel.x / max_cost } else { 0.0 };
+            image.set_pixel(idx % self.width, idx / self.width, Color::new_heatmap(t));
+        }
+        image
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use tokio;
-    use flume::bounded;
 
     use crate::utils::image::Color;
 
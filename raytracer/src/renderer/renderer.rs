@@ -4,9 +4,9 @@ use flume::bounded;
 use indicatif::ProgressBar;
 use tokio::{self, task::JoinHandle};
 
-use crate::{camera::Camera, hittable::world::World, math::vec3::Vec3, utils::image::Image};
+use crate::{camera::Camera, hittable::{accelerator::AcceleratorType, stats::TraversalStats, world::World}, lighting::MisHeuristic, math::vec3::Vec3, utils::image::Image};
 
-use super::{imager::Imager, pointgen::SamplePointGenerator, sampler::{CpuSampler, Sampler}};
+use super::{imager::Imager, pointgen::SamplePointGenerator, sampler::{BdptSampler, CpuSampler, Sampler}};
 
 #[derive(Clone, Copy)]
 pub struct Renderer {
@@ -15,6 +15,10 @@ pub struct Renderer {
     max_bounces: usize,
     progressbar: bool,
     background_color: Vec3,
+    accelerator: AcceleratorType,
+    mis_heuristic: MisHeuristic,
+    roulette_depth: Option<usize>,
+    bidirectional: bool,
 }
 
 impl Renderer {
@@ -30,11 +34,56 @@ impl Renderer {
             num_sampler_threads,
             max_bounces,
             progressbar,
-            background_color: background_color.unwrap_or(Vec3::zero())
+            background_color: background_color.unwrap_or(Vec3::zero()),
+            accelerator: AcceleratorType::default(),
+            mis_heuristic: MisHeuristic::default(),
+            roulette_depth: None,
+            bidirectional: false,
         }
     }
 
+    pub fn with_accelerator(mut self, accelerator: AcceleratorType) -> Self {
+        self.accelerator = accelerator;
+        self
+    }
+
+    pub fn with_mis_heuristic(mut self, mis_heuristic: MisHeuristic) -> Self {
+        self.mis_heuristic = mis_heuristic;
+        self
+    }
+
+    // Bounces before Russian roulette may end a path, see CpuSampler::with_russian_roulette
+    pub fn with_russian_roulette(mut self, min_depth: usize) -> Self {
+        self.roulette_depth = Some(min_depth);
+        self
+    }
+
+    // Renders with BdptSampler instead of CpuSampler, for caustics and lights
+    // reached through narrow openings; the heatmap still traces camera rays only.
+    // BDPT ignores with_russian_roulette, its subpaths are only cut at max_bounces.
+    pub fn with_bidirectional(mut self) -> Self {
+        self.bidirectional = true;
+        self
+    }
+
     pub fn render(&self, camera: Camera, world: Arc<World>) -> JoinHandle<Image> {
+        let handle = self.render_with_stats(camera, world);
+        tokio::spawn(async move {
+            let (image, _) = handle.await.expect("failed to join render thread");
+            image
+        })
+    }
+
+    pub fn render_with_stats(&self, camera: Camera, world: Arc<World>) -> JoinHandle<(Image, TraversalStats)> {
+        self.spawn_render(camera, world, false)
+    }
+
+    // Debug render: each pixel shows how expensive the camera ray was to trace
+    pub fn render_heatmap(&self, camera: Camera, world: Arc<World>) -> JoinHandle<(Image, TraversalStats)> {
+        self.spawn_render(camera, world, true)
+    }
+
+    fn spawn_render(&self, camera: Camera, world: Arc<World>, heatmap: bool) -> JoinHandle<(Image, TraversalStats)> {
         let (width, height) = camera.get_image_size();
         let point_generator = SamplePointGenerator::new(
             width, 
@@ -46,7 +95,19 @@ impl Renderer {
             self.num_sampler_threads,
             self.max_bounces,
             self.background_color,
-        );
+        ).with_accelerator(self.accelerator).with_mis_heuristic(self.mis_heuristic);
+        let sampler = match self.roulette_depth {
+            Some(min_depth) => sampler.with_russian_roulette(min_depth),
+            None => sampler,
+        };
+        let bidirectional = (self.bidirectional && !heatmap).then(|| {
+            BdptSampler::new(
+                camera.clone(),
+                self.num_sampler_threads,
+                self.max_bounces,
+                self.background_color,
+            ).with_accelerator(self.accelerator).with_mis_heuristic(self.mis_heuristic)
+        });
         let progressbar = if self.progressbar {
             Some(Box::new(ProgressBar::new((width*height) as u64)))
         } else {
@@ -58,23 +119,36 @@ impl Renderer {
             self.samples_per_pixel,
             progressbar
         );
+        let (sampler, imager) = if heatmap {
+            (sampler.with_traversal_heatmap(), imager.with_heatmap())
+        } else {
+            (sampler, imager)
+        };
 
         tokio::spawn(async move {
             let (ptx, prx) = bounded(10240);
             let (ctx, crx) = bounded(10240);
+            let (stx, srx) = bounded(10240);
             let point_generator_handle = tokio::spawn(async move {
                 point_generator.generate(ptx).await;
             });
             let sampler_handle = tokio::spawn(async move {
-                sampler.sampling(&world, prx, ctx).await;
+                match bidirectional {
+                    Some(sampler) => sampler.sampling_with_stats(&world, prx, ctx, stx).await,
+                    None => {
+                        drop(stx);
+                        sampler.sampling_with_stats(&world, prx, ctx).await
+                    }
+                }
             });
             let imager_handle = tokio::spawn(async move {
-                imager.collect(crx).await
+                imager.collect_with_splats(crx, srx).await
             });
 
             point_generator_handle.await.expect("failed to join point generator thread");
-            sampler_handle.await.expect("failed to join sampler thread");
-            imager_handle.await.expect("failed to join imager thread")
+            let stats = sampler_handle.await.expect("failed to join sampler thread");
+            let image = imager_handle.await.expect("failed to join imager thread");
+            (image, stats)
         })
     }
 }
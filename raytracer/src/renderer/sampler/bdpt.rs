@@ -0,0 +1,779 @@
+use std::sync::Arc;
+
+use flume::{Receiver, Sender};
+use tokio::task::JoinHandle;
+
+use crate::{camera::Camera, hittable::{accelerator::{Accelerator, AcceleratorType}, stats::{take_ray_cost, TraversalStats}, world::World, HitRecord}, lighting::{Lights, MisHeuristic}, math::vec3::Vec3, ray::Ray, renderer::{imager::SampledColor, pointgen::SamplePoint}, utils::spectrum, Float};
+
+#[derive(Clone, Copy, PartialEq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+// What a subpath carries: radiance on the camera side, importance on the light side
+#[derive(Clone, Copy, PartialEq)]
+enum TransportMode {
+    Radiance,
+    Importance,
+}
+
+#[derive(Clone)]
+struct Vertex {
+    kind: VertexKind,
+    point: Vec3,
+    // geometric normal, zero where there is no surface
+    normal: Vec3,
+    rec: Option<HitRecord>,
+    // unit direction towards the previous vertex of the subpath
+    wo: Vec3,
+    beta: Vec3,
+    mode: TransportMode,
+    // wavelength the vertex is evaluated at, once a dispersive surface picked one
+    wavelength: Option<Float>,
+    // scattered by a specular lobe, so no connection can end here
+    delta: bool,
+    // area densities of sampling this vertex from the previous vertex of its
+    // subpath, and from the next one had the path been traced the other way
+    pdf_fwd: Float,
+    pdf_rev: Float,
+    // the light the vertex sits on, and whether rays can reach it by chance
+    light: Option<usize>,
+    hittable: bool,
+}
+
+impl Vertex {
+    fn camera(point: Vec3) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            point,
+            normal: Vec3::zero(),
+            rec: None,
+            wo: Vec3::zero(),
+            beta: Vec3::new_diagonal(1.0),
+            mode: TransportMode::Radiance,
+            wavelength: None,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            light: None,
+            hittable: false,
+        }
+    }
+
+    fn light(light: usize, point: Vec3, normal: Vec3, beta: Vec3, pdf_fwd: Float, hittable: bool) -> Self {
+        Self {
+            kind: VertexKind::Light,
+            point,
+            normal,
+            rec: None,
+            wo: Vec3::zero(),
+            beta,
+            mode: TransportMode::Importance,
+            wavelength: None,
+            delta: false,
+            pdf_fwd,
+            pdf_rev: 0.0,
+            light: Some(light),
+            hittable,
+        }
+    }
+
+    fn surface(rec: HitRecord, wo: Vec3, beta: Vec3, mode: TransportMode) -> Self {
+        Self {
+            kind: VertexKind::Surface,
+            point: rec.point,
+            normal: rec.geometric_normal,
+            wavelength: rec.wavelength,
+            rec: Some(rec),
+            wo,
+            beta,
+            mode,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            light: None,
+            hittable: true,
+        }
+    }
+
+    fn is_on_surface(&self) -> bool {
+        !self.normal.near_zero()
+    }
+
+    // Whether another vertex can be joined to this one by a shadow ray
+    fn is_connectible(&self) -> bool {
+        match &self.rec {
+            Some(rec) => rec.material.lobes().has_non_specular(),
+            None => true,
+        }
+    }
+
+    // BSDF between direction and the previous vertex, for light flowing from the
+    // light side to the camera side: a light subpath vertex evaluates the adjoint
+    fn f(&self, direction: &Vec3) -> Vec3 {
+        match (&self.rec, self.mode) {
+            (Some(rec), TransportMode::Radiance) => rec.material.eval(rec, &self.wo, direction),
+            (Some(rec), TransportMode::Importance) => rec.material.eval(rec, direction, &self.wo),
+            (None, _) => Vec3::zero(),
+        }
+    }
+
+    fn shading_cos(&self, direction: &Vec3) -> Float {
+        match &self.rec {
+            Some(rec) => rec.normal.dot(direction).abs(),
+            None => 1.0,
+        }
+    }
+
+    // Converts the solid angle density of leaving this vertex towards next to area density at next
+    fn convert_density(&self, pdf: Float, next: &Vertex) -> Float {
+        let to_next = next.point - self.point;
+        let distance_squared = to_next.squared_length();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        if next.is_on_surface() {
+            pdf * next.normal.dot(&to_next).abs() / (distance_squared * distance_squared.sqrt())
+        } else {
+            pdf / distance_squared
+        }
+    }
+}
+
+// The scene a sampling thread traces against, with its traversal counters
+struct Tracer<'a> {
+    world: &'a Arc<dyn Accelerator>,
+    lights: &'a Lights,
+    stats: &'a mut TraversalStats,
+}
+
+impl Tracer<'_> {
+    fn hit(&mut self, ray: &Ray) -> Option<HitRecord> {
+        take_ray_cost();
+        let hit = self.world.hit(ray, 0.001..Float::INFINITY);
+        self.stats.record(take_ray_cost());
+        hit
+    }
+
+    fn unoccluded(&mut self, origin: &Vec3, target: &Vec3) -> bool {
+        let to_target = *target - *origin;
+        let distance = to_target.length();
+        take_ray_cost();
+        let occluded = self.world.hit(&Ray::new(*origin, to_target), 0.001..distance - 0.001).is_some();
+        self.stats.record(take_ray_cost());
+        !occluded
+    }
+}
+
+// Bidirectional path tracer: every camera path is joined with a light subpath
+// at each pair of vertices, and each join weighted with multiple importance
+// sampling against all the other ways of building the same path. Joining light
+// vertices to the lens splats to whichever pixel they land in, so it needs a
+// splat channel next to the usual sample channel.
+//
+// Light subpaths start on the lights that have a position; the environment,
+// sky and directional lights are reached from the camera side only and
+// weighted as by CpuSampler. Paths go straight through the interior of
+// subsurface media.
+#[derive(Clone)]
+pub struct BdptSampler {
+    camera: Camera,
+    num_threads: usize,
+    max_bounces: usize,
+    background_color: Vec3,
+    accelerator: AcceleratorType,
+    mis_heuristic: MisHeuristic,
+}
+
+impl BdptSampler {
+    pub fn new(
+        camera: Camera,
+        num_threads: usize,
+        max_bounces: usize,
+        background_color: Vec3,
+    ) -> Self {
+        Self { camera, num_threads, max_bounces, background_color, accelerator: AcceleratorType::default(), mis_heuristic: MisHeuristic::default() }
+    }
+
+    pub fn with_accelerator(mut self, accelerator: AcceleratorType) -> Self {
+        self.accelerator = accelerator;
+        self
+    }
+
+    pub fn with_mis_heuristic(mut self, mis_heuristic: MisHeuristic) -> Self {
+        self.mis_heuristic = mis_heuristic;
+        self
+    }
+
+    pub async fn sampling_with_stats(
+        self,
+        world: &World,
+        in_channel: Receiver<SamplePoint>,
+        out_channel: Sender<SampledColor>,
+        splat_channel: Sender<SampledColor>,
+    ) -> TraversalStats {
+        let lights = Arc::new(world.get_lights());
+        let world = world.get_accelerator(self.accelerator);
+        let handles: Vec<JoinHandle<TraversalStats>> = (0..self.num_threads).map(|_| {
+            let sampler = self.clone();
+            let world = world.clone();
+            let lights = lights.clone();
+            let in_channel = in_channel.clone();
+            let out_channel = out_channel.clone();
+            let splat_channel = splat_channel.clone();
+            tokio::spawn(async move {
+                sampler.sampling_subthread(world, lights, in_channel, out_channel, splat_channel).await
+            })
+        }).collect();
+        let mut stats = TraversalStats::new();
+        for handle in handles {
+            let thread_stats = handle.await.expect("failed to join sampling thread");
+            stats = TraversalStats::merge(stats, thread_stats);
+        }
+        stats
+    }
+
+    async fn sampling_subthread(
+        &self,
+        world: Arc<dyn Accelerator>,
+        lights: Arc<Lights>,
+        in_channel: Receiver<SamplePoint>,
+        out_channel: Sender<SampledColor>,
+        splat_channel: Sender<SampledColor>,
+    ) -> TraversalStats {
+        let mut stats = TraversalStats::new();
+        let mut splats = Vec::new();
+        while let Ok(sample_point) = in_channel.recv_async().await {
+            let sampled_color = self.single_point_sampling(&world, &lights, sample_point, &mut splats, &mut stats);
+            out_channel.send_async(sampled_color)
+                       .await.expect("failed to send sampled color");
+            for splat in splats.drain(..) {
+                splat_channel.send_async(splat)
+                             .await.expect("failed to send splat");
+            }
+        }
+        stats
+    }
+
+    fn single_point_sampling(
+        &self,
+        world: &Arc<dyn Accelerator>,
+        lights: &Lights,
+        sample_point: SamplePoint,
+        splats: &mut Vec<SampledColor>,
+        stats: &mut TraversalStats,
+    ) -> SampledColor {
+        let mut tracer = Tracer { world, lights, stats };
+        let ray = sample_point.ray;
+        let mut color = Vec3::zero();
+
+        let mut camera_path = vec![Vertex::camera(ray.origin())];
+        let pdf = self.camera.pdf_direction(&ray.direction());
+        let escaped = self.random_walk(&mut tracer, TransportMode::Radiance, ray, Vec3::new_diagonal(1.0), pdf, &mut camera_path);
+        if let Some((beta, direction, pdf)) = escaped {
+            color += beta * self.escaped_radiance(lights, &camera_path, &direction, pdf);
+        }
+        let wavelength = camera_path[camera_path.len() - 1].wavelength;
+        let light_path = self.light_subpath(&mut tracer, wavelength);
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                // s + t - 2 bounces
+                if (s == 1 && t == 1) || s + t < 2 || s + t - 2 > self.max_bounces {
+                    continue;
+                }
+                let Some((contribution, pixel)) = self.connect(&mut tracer, &light_path, &camera_path, s, t) else {
+                    continue;
+                };
+                match pixel {
+                    Some((x, y)) => splats.push(SampledColor { x: x as u32, y: y as u32, color: contribution }),
+                    None => color += contribution,
+                }
+            }
+        }
+
+        SampledColor { x: sample_point.x, y: sample_point.y, color }
+    }
+
+    // Extends path from its last vertex along ray, starting with throughput beta and
+    // the solid angle density pdf of the ray, until the path is max_bounces deep or ends.
+    // Returns the throughput, direction and density of a ray that left the scene.
+    fn random_walk(
+        &self,
+        tracer: &mut Tracer,
+        mode: TransportMode,
+        mut ray: Ray,
+        mut beta: Vec3,
+        mut pdf: Float,
+        path: &mut Vec<Vertex>,
+    ) -> Option<(Vec3, Vec3, Float)> {
+        // the camera side also holds the camera, and one vertex for the light it may hit
+        let max_vertices = match mode {
+            TransportMode::Radiance => self.max_bounces + 2,
+            TransportMode::Importance => self.max_bounces + 1,
+        };
+        let mut wavelength = path[path.len() - 1].wavelength;
+        while path.len() < max_vertices {
+            let Some(mut rec) = tracer.hit(&ray) else {
+                return Some((beta, ray.direction(), pdf));
+            };
+            rec.wavelength = wavelength;
+            let wo = -ray.direction();
+            let mut vertex = Vertex::surface(rec.clone(), wo, beta, mode);
+            vertex.pdf_fwd = path[path.len() - 1].convert_density(pdf, &vertex);
+            path.push(vertex);
+            if path.len() >= max_vertices {
+                break;
+            }
+
+            let sample = rec.material.sample(&rec, &wo)?;
+            let n = path.len();
+            let pdf_rev = if sample.lobe.is_specular() {
+                path[n - 1].delta = true;
+                pdf = 0.0;
+                0.0
+            } else {
+                pdf = sample.pdf;
+                rec.material.pdf(&rec, &sample.wi, &wo)
+            };
+            path[n - 2].pdf_rev = path[n - 1].convert_density(pdf_rev, &path[n - 2]);
+            beta *= sample.weight;
+            if mode == TransportMode::Importance {
+                // importance isn't compressed into a smaller solid angle on refraction the way radiance is
+                beta *= sample.eta * sample.eta;
+            }
+            if beta.near_zero() {
+                break;
+            }
+            wavelength = sample.wavelength.or(wavelength);
+            ray = Ray::new(rec.point, sample.wi);
+        }
+        None
+    }
+
+    // Traced at the wavelength of the camera path when it has one, so both
+    // sides of a connection refract alike
+    fn light_subpath(&self, tracer: &mut Tracer, wavelength: Option<Float>) -> Vec<Vertex> {
+        let Some((index, probability, emission)) = tracer.lights.sample_emission() else {
+            return Vec::new();
+        };
+        if emission.pdf_position <= 0.0 || emission.pdf_direction <= 0.0 || emission.radiance.near_zero() {
+            return Vec::new();
+        }
+        let pdf_origin = probability * emission.pdf_position;
+        let mut path = vec![Vertex::light(
+            index,
+            emission.point,
+            emission.normal,
+            emission.radiance / pdf_origin,
+            pdf_origin,
+            emission.hittable,
+        )];
+        path[0].wavelength = wavelength;
+        let cos = if emission.normal.near_zero() { 1.0 } else { emission.normal.dot(&emission.direction).abs() };
+        let beta = emission.radiance * cos / (pdf_origin * emission.pdf_direction);
+        let ray = Ray::new(emission.point, emission.direction);
+        self.random_walk(tracer, TransportMode::Importance, ray, beta, emission.pdf_direction, &mut path);
+        path
+    }
+
+    // Light from lights at infinity, weighted against sampling them from the last vertex
+    fn escaped_radiance(&self, lights: &Lights, camera_path: &[Vertex], direction: &Vec3, pdf: Float) -> Vec3 {
+        let Some(radiance) = lights.background(direction) else {
+            return self.background_color;
+        };
+        let last = &camera_path[camera_path.len() - 1];
+        if last.kind == VertexKind::Camera || pdf == 0.0 {
+            return radiance;
+        }
+        radiance * self.mis_heuristic.weight(pdf, lights.pdf(&last.point, direction, true))
+    }
+
+    // Weighted contribution of the path made of the first s light and t camera
+    // vertices, with the pixel it splats to when it goes through the lens (t = 1)
+    fn connect(
+        &self,
+        tracer: &mut Tracer,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        s: usize,
+        t: usize,
+    ) -> Option<(Vec3, Option<(usize, usize)>)> {
+        let lights = tracer.lights;
+        let mut sampled = None;
+        let mut pixel = None;
+        let contribution = if s == 0 {
+            // the camera path hit a light by itself
+            let pt = &camera_path[t - 1];
+            let rec = pt.rec.as_ref()?;
+            pt.beta * rec.material.emitted(rec, &pt.wo)?
+        } else if t == 1 {
+            // light tracing: the light vertex seen through a point on the lens
+            let qs = &light_path[s - 1];
+            if !qs.is_connectible() {
+                return None;
+            }
+            let lens_point = self.camera.sample_lens();
+            let (lens_pixel, importance) = self.camera.importance(&lens_point, &qs.point)?;
+            let wi = (lens_point - qs.point).normalized();
+            let contribution = qs.beta * qs.f(&wi) * qs.shading_cos(&wi) * importance;
+            if contribution.near_zero() || !tracer.unoccluded(&qs.point, &lens_point) {
+                return None;
+            }
+            pixel = Some(lens_pixel);
+            sampled = Some(Vertex::camera(lens_point));
+            contribution
+        } else if s == 1 {
+            // next event estimation from the last camera vertex
+            let pt = &camera_path[t - 1];
+            if !pt.is_connectible() {
+                return None;
+            }
+            let (index, light) = lights.sample_light(&pt.point)?;
+            if light.pdf <= 0.0 {
+                return None;
+            }
+            let contribution = pt.beta * pt.f(&light.wi) * light.radiance * pt.shading_cos(&light.wi) / light.pdf;
+            let point = pt.point + light.wi * light.distance;
+            if contribution.near_zero() || !tracer.unoccluded(&pt.point, &point) {
+                return None;
+            }
+            if lights.emitter(index).bounds().is_none() {
+                let weight = if light.hittable {
+                    let rec = pt.rec.as_ref()?;
+                    self.mis_heuristic.weight(light.pdf, rec.material.pdf(rec, &pt.wo, &light.wi))
+                } else {
+                    1.0
+                };
+                return Some((contribution * weight, None));
+            }
+            let mut vertex = Vertex::light(index, point, light.normal, light.radiance / light.pdf, 0.0, light.hittable);
+            vertex.pdf_fwd = self.light_origin_pdf(lights, &vertex, pt);
+            sampled = Some(vertex);
+            contribution
+        } else {
+            let qs = &light_path[s - 1];
+            let pt = &camera_path[t - 1];
+            if !qs.is_connectible() || !pt.is_connectible() {
+                return None;
+            }
+            let to_pt = pt.point - qs.point;
+            let distance_squared = to_pt.squared_length();
+            let direction = to_pt / distance_squared.sqrt();
+            let contribution = qs.beta * qs.f(&direction) * pt.f(&-direction) * pt.beta
+                * qs.shading_cos(&direction) * pt.shading_cos(&direction) / distance_squared;
+            if contribution.near_zero() || !tracer.unoccluded(&qs.point, &pt.point) {
+                return None;
+            }
+            contribution
+        };
+        // a light subpath traced at the camera path's wavelength leaves its RGB weight to the
+        // camera side, which only carries it from the vertex that picked the wavelength on
+        let contribution = match light_path.first().and_then(|light| light.wavelength) {
+            Some(wavelength) if s >= 2 && camera_path[t - 1].wavelength.is_none() => {
+                contribution * spectrum::wavelength_to_rgb(wavelength)
+            }
+            _ => contribution,
+        };
+        let weight = self.mis_weight(lights, light_path, camera_path, sampled, s, t);
+        Some((contribution * weight, pixel))
+    }
+
+    // Area density of sampling next from vertex, which the subpath reached from prev
+    fn pdf(&self, lights: &Lights, vertex: &Vertex, prev: Option<&Vertex>, next: &Vertex) -> Float {
+        match vertex.kind {
+            VertexKind::Light => self.light_pdf(lights, vertex, next),
+            VertexKind::Camera => {
+                let direction = (next.point - vertex.point).normalized();
+                vertex.convert_density(self.camera.pdf_direction(&direction), next)
+            }
+            VertexKind::Surface => {
+                let (Some(rec), Some(prev)) = (&vertex.rec, prev) else {
+                    return 0.0;
+                };
+                let wo = (prev.point - vertex.point).normalized();
+                let wi = (next.point - vertex.point).normalized();
+                vertex.convert_density(rec.material.pdf(rec, &wo, &wi), next)
+            }
+        }
+    }
+
+    // Area density of a light subpath leaving the light at vertex towards next
+    fn light_pdf(&self, lights: &Lights, vertex: &Vertex, next: &Vertex) -> Float {
+        let Some(index) = vertex.light else {
+            return 0.0;
+        };
+        let direction = (next.point - vertex.point).normalized();
+        let (_, pdf_direction) = lights.emitter(index).pdf_emission(&vertex.point, &vertex.normal, &direction);
+        vertex.convert_density(pdf_direction, next)
+    }
+
+    // Density of a light subpath starting at vertex
+    fn light_origin_pdf(&self, lights: &Lights, vertex: &Vertex, next: &Vertex) -> Float {
+        let Some(index) = vertex.light else {
+            return 0.0;
+        };
+        let direction = (next.point - vertex.point).normalized();
+        let (pdf_position, _) = lights.emitter(index).pdf_emission(&vertex.point, &vertex.normal, &direction);
+        lights.emission_probability(index) * pdf_position
+    }
+
+    // Weight of connecting s light and t camera vertices against every other split
+    // of the same path, from the ratios of the densities each split samples it with
+    fn mis_weight(
+        &self,
+        lights: &Lights,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        sampled: Option<Vertex>,
+        s: usize,
+        t: usize,
+    ) -> Float {
+        if s + t == 2 {
+            return 1.0;
+        }
+        let mut light_vertices = light_path[..s].to_vec();
+        let mut camera_vertices = camera_path[..t].to_vec();
+        match sampled {
+            Some(vertex) if s == 1 => light_vertices[0] = vertex,
+            Some(vertex) if t == 1 => camera_vertices[0] = vertex,
+            _ => (),
+        }
+        if s == 0 {
+            // emissive geometry that isn't one of the lights can't be reached any other way
+            let (pt, prev) = (&camera_vertices[t - 1], &camera_vertices[t - 2]);
+            let distance = (pt.point - prev.point).length();
+            let Some(index) = lights.find(&prev.point, &-pt.wo, distance) else {
+                return 1.0;
+            };
+            camera_vertices[t - 1].light = Some(index);
+        }
+
+        // densities of the vertices around the connection, had the other side sampled them
+        let (lv, cv) = (&light_vertices, &camera_vertices);
+        let pt_rev = if s > 0 {
+            self.pdf(lights, &lv[s - 1], s.checked_sub(2).map(|i| &lv[i]), &cv[t - 1])
+        } else {
+            self.light_origin_pdf(lights, &cv[t - 1], &cv[t - 2])
+        };
+        let pt_minus_rev = (t > 1).then(|| if s > 0 {
+            self.pdf(lights, &cv[t - 1], Some(&lv[s - 1]), &cv[t - 2])
+        } else {
+            self.light_pdf(lights, &cv[t - 1], &cv[t - 2])
+        });
+        let qs_rev = (s > 0).then(|| self.pdf(lights, &cv[t - 1], t.checked_sub(2).map(|i| &cv[i]), &lv[s - 1]));
+        let qs_minus_rev = (s > 1).then(|| self.pdf(lights, &lv[s - 1], Some(&cv[t - 1]), &lv[s - 2]));
+
+        camera_vertices[t - 1].pdf_rev = pt_rev;
+        camera_vertices[t - 1].delta = false;
+        if let Some(pdf) = pt_minus_rev {
+            camera_vertices[t - 2].pdf_rev = pdf;
+        }
+        if let Some(pdf) = qs_rev {
+            light_vertices[s - 1].pdf_rev = pdf;
+            light_vertices[s - 1].delta = false;
+        }
+        if let Some(pdf) = qs_minus_rev {
+            light_vertices[s - 2].pdf_rev = pdf;
+        }
+
+        // zero densities come from specular vertices, which are skipped below anyway
+        let remap = |pdf: Float| if pdf != 0.0 { pdf } else { 1.0 };
+        let exponent = match self.mis_heuristic {
+            MisHeuristic::Balance => 1,
+            MisHeuristic::Power => 2,
+        };
+        let mut sum = 0.0;
+        let mut ratio = 1.0;
+        for i in (1..t).rev() {
+            ratio *= remap(camera_vertices[i].pdf_rev) / remap(camera_vertices[i].pdf_fwd);
+            if !camera_vertices[i].delta && !camera_vertices[i - 1].delta {
+                sum += ratio.powi(exponent);
+            }
+        }
+        ratio = 1.0;
+        for i in (0..s).rev() {
+            ratio *= remap(light_vertices[i].pdf_rev) / remap(light_vertices[i].pdf_fwd);
+            // the split with no light vertices needs the camera path to hit the light
+            let reachable = if i > 0 { !light_vertices[i - 1].delta } else { light_vertices[0].hittable };
+            if !light_vertices[i].delta && reachable {
+                sum += ratio.powi(exponent);
+            }
+        }
+        1.0 / (1.0 + sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hittable::{quad::Quad, sphere::Sphere}, material::{dielectric::Dielectric, dispersive::{DispersiveDielectric, Ior}, lambertian::Lambertian, light::Light, roughdielectric::RoughDielectric, Material}, renderer::Renderer, utils::image::Image};
+
+    use super::*;
+
+    fn lamp_world(ball: Box<dyn Material>) -> World {
+        let mut world = World::new();
+        world.add_material("white", Box::new(Lambertian::new(Vec3::new_diagonal(0.7))));
+        world.add_material("ball", ball);
+        world.add_material("lamp", Box::new(Light::new(Vec3::new_diagonal(8.0))));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-2.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            world.get_material("white").unwrap(),
+        )));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-2.0, 0.0, -1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            world.get_material("white").unwrap(),
+        )));
+        world.add_geometry(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.4, 0.0),
+            0.4,
+            world.get_material("ball").unwrap(),
+        )));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-0.3, 1.5, -0.3),
+            Vec3::new(0.6, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.6),
+            world.get_material("lamp").unwrap(),
+        )));
+        world
+    }
+
+    // The lamp world with a bulb inside the ball, so every light subpath leaves through it
+    fn bulb_world(ball: Box<dyn Material>) -> World {
+        let mut world = lamp_world(ball);
+        world.add_material("bulb", Box::new(Light::new(Vec3::new_diagonal(4.0))));
+        world.add_geometry(Box::new(Sphere::new(Vec3::new(0.0, 0.4, 0.0), 0.3, world.get_material("bulb").unwrap())));
+        world
+    }
+
+    // Image average in linear color
+    fn mean(image: &Image) -> Vec3 {
+        let (width, height) = image.size();
+        let mut sum = Vec3::zero();
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = image.get_pixel(x, y);
+                sum += Vec3::new(pixel.r.powf(2.2), pixel.g.powf(2.2), pixel.b.powf(2.2));
+            }
+        }
+        sum / (width * height) as Float
+    }
+
+    fn assert_means_match(reference: &Image, bidirectional: &Image, tolerance: Float) {
+        let (expected, actual) = (mean(reference), mean(bidirectional));
+        for channel in 0..3 {
+            assert!(expected[channel] > 0.01);
+            assert!((actual[channel] - expected[channel]).abs() < tolerance * expected[channel], "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    // Linear averages over square blocks of the image, row by row
+    fn block_means(image: &Image, block: usize) -> Vec<Float> {
+        let (width, height) = image.size();
+        let mut means = Vec::new();
+        for by in 0..height / block {
+            for bx in 0..width / block {
+                let mut sum = 0.0;
+                for y in by * block..(by + 1) * block {
+                    for x in bx * block..(bx + 1) * block {
+                        sum += image.get_pixel(x, y).r.powf(2.2);
+                    }
+                }
+                means.push(sum / (block * block) as Float);
+            }
+        }
+        means
+    }
+
+    fn test_camera() -> Camera {
+        Camera::new(
+            3.0,
+            0.0,
+            Vec3::new(0.0, 1.0, 3.0),
+            Vec3::new(0.0, 0.5, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            24, 18,
+        )
+    }
+
+    async fn render_both(world: World, samples_per_pixel: usize) -> (Image, Image) {
+        let world = Arc::new(world);
+        let camera = test_camera();
+        let renderer = Renderer::new(samples_per_pixel, 4, 4, false, None);
+        let reference = renderer.render(camera.clone(), world.clone()).await.expect("failed to join render thread");
+        let bidirectional = renderer.with_bidirectional().render(camera, world).await.expect("failed to join render thread");
+        (reference, bidirectional)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_matches_path_tracing() {
+        let world = lamp_world(Box::new(Lambertian::new(Vec3::new_diagonal(0.7))));
+        let (reference, bidirectional) = render_both(world, 64).await;
+        assert_means_match(&reference, &bidirectional, 0.05);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_rough_glass() {
+        // light subpaths leave the ball refracting, carrying importance,
+        // which isn't compressed into a smaller solid angle like radiance
+        let world = bulb_world(Box::new(RoughDielectric::new(1.5, 0.3)));
+        let (reference, bidirectional) = render_both(world, 128).await;
+        assert_means_match(&reference, &bidirectional, 0.05);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_smooth_glass() {
+        // caustic under the ball, through vertices no connection can end on
+        let world = lamp_world(Box::new(Dielectric::new(Vec3::new_diagonal(1.0), 1.5)));
+        let (reference, bidirectional) = render_both(world, 128).await;
+        assert_means_match(&reference, &bidirectional, 0.05);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_dispersive_glass() {
+        // both subpaths refract through the ball, and have to do it at the same wavelength
+        let world = bulb_world(Box::new(DispersiveDielectric::new(Ior::DENSE_FLINT)));
+        let (reference, bidirectional) = render_both(world, 128).await;
+        assert_means_match(&reference, &bidirectional, 0.05);
+    }
+
+    #[test]
+    fn test_light_subpath_wavelength() {
+        // a light subpath given the camera's wavelength refracts at it, without re-weighting
+        let world = bulb_world(Box::new(DispersiveDielectric::new(Ior::DENSE_FLINT)));
+        let lights = world.get_lights();
+        let accelerator = world.get_accelerator(AcceleratorType::default());
+        let mut stats = TraversalStats::new();
+        let mut tracer = Tracer { world: &accelerator, lights: &lights, stats: &mut stats };
+        let sampler = BdptSampler::new(test_camera(), 1, 8, Vec3::zero());
+        let mut surfaces = 0;
+        for _ in 0..200 {
+            for vertex in sampler.light_subpath(&mut tracer, Some(550.0)) {
+                assert_eq!(vertex.wavelength, Some(550.0));
+                assert!(vertex.beta.x == vertex.beta.y && vertex.beta.y == vertex.beta.z, "{:?}", vertex.beta);
+                if vertex.kind == VertexKind::Surface {
+                    surfaces += 1;
+                }
+            }
+        }
+        assert!(surfaces > 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_splat_placement() {
+        // light traced through the lens must brighten the same parts of the image
+        let world = lamp_world(Box::new(Lambertian::new(Vec3::new_diagonal(0.7))));
+        let (reference, bidirectional) = render_both(world, 128).await;
+        let expected = block_means(&reference, 6);
+        let actual = block_means(&bidirectional, 6);
+        for (i, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+            assert!((actual - expected).abs() < 0.1 * expected.max(0.05), "block {}: {} != {}", i, actual, expected);
+        }
+    }
+}
@@ -4,15 +4,24 @@ use std::{future::Future, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Dur
 use tokio::{task::{yield_now, JoinHandle}, time::{sleep, timeout}};
 use flume::{bounded, Receiver, Sender};
 
-use crate::{hittable::{bvh::BVH, world::World, Hittable}, math::vec3::Vec3, renderer::{imager::SampledColor, pointgen::SamplePoint}, Float};
+use crate::{hittable::{accelerator::{Accelerator, AcceleratorType}, stats::{take_ray_cost, TraversalStats}, world::World, HitRecord, Hittable}, lighting::{Lights, MisHeuristic}, material::medium::Medium, math::vec3::Vec3, ray::Ray, renderer::{imager::SampledColor, pointgen::SamplePoint}, utils::random::random, Float};
 
 use super::Sampler;
 
+// Scattering events a path may take inside subsurface media, on top of max_bounces
+const MAX_WALK_STEPS: usize = 256;
+// Even bright paths are ended now and then, so loops between mirrors terminate
+const MAX_SURVIVAL: Float = 0.95;
+
 #[derive(Clone, Copy)]
 pub struct CpuSampler {
     num_threads: usize,
     max_bounces: usize,
     background_color: Vec3,
+    traversal_heatmap: bool,
+    accelerator: AcceleratorType,
+    mis_heuristic: MisHeuristic,
+    roulette_depth: Option<usize>,
 }
 
 impl CpuSampler {
@@ -21,48 +30,205 @@ impl CpuSampler {
         max_bounces: usize,
         background_color: Vec3,
     ) -> Self {
-        Self { num_threads, max_bounces, background_color }
+        Self { num_threads, max_bounces, background_color, traversal_heatmap: false, accelerator: AcceleratorType::default(), mis_heuristic: MisHeuristic::default(), roulette_depth: None }
+    }
+
+    pub fn with_accelerator(mut self, accelerator: AcceleratorType) -> Self {
+        self.accelerator = accelerator;
+        self
+    }
+
+    pub fn with_mis_heuristic(mut self, mis_heuristic: MisHeuristic) -> Self {
+        self.mis_heuristic = mis_heuristic;
+        self
+    }
+
+    // Paths deeper than min_depth bounces survive with a probability that follows
+    // their throughput and are reweighted to stay unbiased. max_bounces still applies.
+    // Off unless set, every path then runs until it escapes or max_bounces.
+    pub fn with_russian_roulette(mut self, min_depth: usize) -> Self {
+        self.roulette_depth = Some(min_depth);
+        self
+    }
+
+    // Instead of shading, trace only the camera ray and report its traversal
+    // cost (nodes visited + primitive tests) in the red channel.
+    pub fn with_traversal_heatmap(mut self) -> Self {
+        self.traversal_heatmap = true;
+        self
+    }
+
+    pub async fn sampling_with_stats(
+        self,
+        world: &World,
+        in_channel: Receiver<SamplePoint>,
+        out_channel: Sender<SampledColor>,
+    ) -> TraversalStats {
+        let lights = Arc::new(world.get_lights());
+        let world = world.get_accelerator(self.accelerator);
+        let handles: Vec<JoinHandle<TraversalStats>> = (0..self.num_threads).map(|_| {
+            let world = world.clone();
+            let lights = lights.clone();
+            let in_channel = in_channel.clone();
+            let out_channel = out_channel.clone();
+            tokio::spawn(async move {
+                self.sampling_subthread(world, lights, in_channel, out_channel).await
+            })
+        }).collect();
+        let mut stats = TraversalStats::new();
+        for handle in handles {
+            let thread_stats = handle.await.expect("failed to join sampling thread");
+            stats = TraversalStats::merge(stats, thread_stats);
+        }
+        stats
     }
 
     async fn sampling_subthread(
         &self, 
-        world: Arc<BVH>,
+        world: Arc<dyn Accelerator>,
+        lights: Arc<Lights>,
         in_channel: Receiver<SamplePoint>,
         out_channel: Sender<SampledColor>,
-    ) {
+    ) -> TraversalStats {
+        let mut stats = TraversalStats::new();
         while let Ok(sample_point) = in_channel.recv_async().await {
-            out_channel.send_async(self.single_point_sampling(world.clone(), sample_point))
+            let sampled_color = if self.traversal_heatmap {
+                self.traversal_cost_sampling(world.clone(), sample_point, &mut stats)
+            } else {
+                self.single_point_sampling(world.clone(), &lights, sample_point, &mut stats)
+            };
+            out_channel.send_async(sampled_color)
                        .await.expect("failed to send sampled color");
         }
+        stats
+    }
+
+    fn traversal_cost_sampling(&self, world: Arc<dyn Accelerator>, sample_point: SamplePoint, stats: &mut TraversalStats) -> SampledColor {
+        take_ray_cost();
+        world.hit(&sample_point.ray, 0.001..Float::INFINITY);
+        let cost = take_ray_cost();
+        stats.record(cost);
+        SampledColor {
+            x: sample_point.x,
+            y: sample_point.y,
+            color: Vec3::new(cost.total() as Float, 0.0, 0.0),
+        }
     }
 
-    fn single_point_sampling(&self, world: Arc<BVH>, sample_point: SamplePoint) -> SampledColor {
+    fn single_point_sampling(&self, world: Arc<dyn Accelerator>, lights: &Lights, sample_point: SamplePoint, stats: &mut TraversalStats) -> SampledColor {
         let x = sample_point.x;
         let y = sample_point.y;
         let mut ray = sample_point.ray;
         let mut remain_bounces = self.max_bounces;
         let mut color = Vec3::zero();
         let mut cumulated_attenuation = Vec3::new_diagonal(Float::from(1.0));
+        let mut wavelength = None;
+        let mut medium: Option<Medium> = None;
+        let mut remain_walk_steps = MAX_WALK_STEPS;
+        // where the previous bounce sampled a light and the BSDF pdf of the direction
+        // it continued in, to weight emission the path hits against the light sample
+        let mut light_sampled_from: Option<(Vec3, Float)> = None;
 
         while remain_bounces > 0 {
-            if let Some(rec) = world.hit(&ray, 0.001..Float::INFINITY) {
-                let emission = rec.material.emitted().unwrap_or(Vec3::zero());
-                color += cumulated_attenuation * emission;
-                if let Some((new_ray, attenuation)) = rec.material.scatter(&ray, &rec) {
-                    cumulated_attenuation *= attenuation;
-                    ray = new_ray;
+            take_ray_cost();
+            let hit = world.hit(&ray, 0.001..Float::INFINITY);
+            stats.record(take_ray_cost());
+            if let Some(interior) = medium {
+                let boundary = hit.as_ref().map_or(Float::INFINITY, |rec| rec.t);
+                let (scatter, weight) = interior.sample_distance(boundary);
+                cumulated_attenuation *= weight;
+                if let Some(t) = scatter {
+                    if remain_walk_steps == 0 {
+                        break;
+                    }
+                    remain_walk_steps -= 1;
+                    ray = Ray::new(ray.at(t), interior.sample_direction(&ray.direction()));
+                    continue;
+                }
+            }
+            if let Some(mut rec) = hit {
+                rec.wavelength = wavelength;
+                let wo = -ray.direction();
+                if let Some(emission) = rec.material.emitted(&rec, &wo) {
+                    // only the light hit could have sampled this, not the ones behind it
+                    let weight = light_sampled_from.map_or(1.0, |(origin, bsdf_pdf)| {
+                        let light_pdf = lights.find(&origin, &ray.direction(), rec.t)
+                            .map_or(0.0, |index| lights.light_pdf(index, &origin, &ray.direction()));
+                        self.mis_heuristic.weight(bsdf_pdf, light_pdf)
+                    });
+                    color += cumulated_attenuation * emission * weight;
+                }
+                // shadow rays can't leave a medium, so lights are only sampled outside
+                let sample_lights = medium.is_none() && rec.material.lobes().has_non_specular();
+                if sample_lights {
+                    // the BSDF sample of the last bounce isn't traced, so the light sample takes all the weight
+                    let mis = remain_bounces > 1;
+                    color += cumulated_attenuation * self.direct_lighting(&world, lights, &rec, &wo, mis, stats);
+                }
+                if let Some(sample) = rec.material.sample(&rec, &wo) {
+                    light_sampled_from = if sample_lights && !sample.lobe.is_specular() {
+                        Some((rec.point, sample.pdf))
+                    } else {
+                        None
+                    };
+                    cumulated_attenuation *= sample.weight;
+                    wavelength = sample.wavelength.or(wavelength);
+                    if let Some(interior) = rec.material.interior() {
+                        let outward_normal = if rec.front_face { rec.normal } else { -rec.normal };
+                        medium = if sample.wi.dot(&outward_normal) < 0.0 { Some(*interior) } else { None };
+                    }
+                    ray = Ray::new(rec.point, sample.wi);
                     remain_bounces -= 1;
+                    if self.roulette_depth.is_some_and(|depth| self.max_bounces - remain_bounces >= depth) {
+                        let survival = cumulated_attenuation.x.max(cumulated_attenuation.y).max(cumulated_attenuation.z).min(MAX_SURVIVAL);
+                        if random::<Float>() >= survival {
+                            break;
+                        }
+                        cumulated_attenuation /= survival;
+                    }
                 } else {
                     break;
                 }
             } else {
-                color += cumulated_attenuation * self.background_color;
+                let direction = ray.direction();
+                if let Some(radiance) = lights.background(&direction) {
+                    let weight = light_sampled_from.map_or(1.0, |(origin, bsdf_pdf)| {
+                        self.mis_heuristic.weight(bsdf_pdf, lights.pdf(&origin, &direction, true))
+                    });
+                    color += cumulated_attenuation * radiance * weight;
+                } else {
+                    color += cumulated_attenuation * self.background_color;
+                }
                 break;
             }
         }
 
         SampledColor { x, y, color }
     }
+
+    // Next event estimation: light from one sampled light reflected towards wo,
+    // weighted against the chance of the BSDF sampling the same direction when mis is set
+    fn direct_lighting(&self, world: &Arc<dyn Accelerator>, lights: &Lights, rec: &HitRecord, wo: &Vec3, mis: bool, stats: &mut TraversalStats) -> Vec3 {
+        let Some(light) = lights.sample(&rec.point) else {
+            return Vec3::zero();
+        };
+        let f = rec.material.eval(rec, wo, &light.wi);
+        if f.near_zero() {
+            return Vec3::zero();
+        }
+        take_ray_cost();
+        let occluded = world.hit(&Ray::new(rec.point, light.wi), 0.001..light.distance - 0.001).is_some();
+        stats.record(take_ray_cost());
+        if occluded {
+            return Vec3::zero();
+        }
+        let weight = if light.hittable && mis {
+            self.mis_heuristic.weight(light.pdf, rec.material.pdf(rec, wo, &light.wi))
+        } else {
+            1.0
+        };
+        f * light.radiance * rec.normal.dot(&light.wi).abs() * weight / light.pdf
+    }
 }
 
 impl Sampler for CpuSampler {
@@ -72,18 +238,7 @@ impl Sampler for CpuSampler {
         in_channel: Receiver<SamplePoint>,
         out_channel: Sender<SampledColor>,
     ) {
-        let world = Arc::new(world.get_bvh());
-        let handles: Vec<JoinHandle<()>> = (0..self.num_threads).map(|_| {
-            let world = world.clone();
-            let in_channel = in_channel.clone();
-            let out_channel = out_channel.clone();
-            tokio::spawn(async move {
-                self.sampling_subthread(world, in_channel, out_channel).await;
-            })
-        }).collect();
-        for handle in handles {
-            handle.await.expect("failed to join sampling thread");
-        }
+        self.sampling_with_stats(world, in_channel, out_channel).await;
     }
 }
 
@@ -94,7 +249,7 @@ mod tests {
     use flume::bounded;
 
     use super::*;
-    use crate::{hittable::sphere::Sphere, material::lambertian::Lambertian, ray::Ray};
+    use crate::{hittable::{quad::Quad, sphere::Sphere}, lighting::environment::EnvironmentLight, material::{bsdf::{BsdfSample, Lobe}, lambertian::Lambertian, light::Light, subsurface::Subsurface, Material}, ray::Ray, texture::image::ImageTexture, FloatConsts};
 
     fn dummy_world() -> Arc<World> {
         let mut world = World::new();
@@ -178,4 +333,272 @@ mod tests {
             assert!(received[i], "failed at i={}", i)
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_traversal_stats() {
+        let num_samples = 100;
+        let world = dummy_world();
+
+        let (tx, rx) = bounded(num_samples);
+        let (ctx, crx) = bounded(num_samples);
+
+        let sampler = CpuSampler::new(1, 2, Vec3::zero()).with_traversal_heatmap();
+        let sampler_handle = tokio::spawn(async move {
+            sampler.sampling_with_stats(&world, rx, ctx).await
+        });
+        let receiver_handle = tokio::spawn(async move {
+            let mut costs = Vec::new();
+            while let Ok(sampled_color) = crx.recv_async().await {
+                costs.push(sampled_color.color.x);
+            }
+            costs
+        });
+
+        for i in 0..num_samples {
+            let t = i as Float / num_samples as Float;
+            let ray = Ray::new(Vec3::new(-1.0 + 2.0 * t, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            tx.send_async(SamplePoint { x: i as u32, y: 0, ray }).await.expect("failed to send sample point");
+        }
+        drop(tx);
+
+        let stats = sampler_handle.await.expect("failed to join sampler thread");
+        let costs = receiver_handle.await.expect("failed to join receive thread");
+        assert_eq!(stats.rays, num_samples as u64);
+        assert!(stats.avg_nodes_visited() >= 1.0);
+        assert!(stats.max_primitive_tests >= 1);
+        assert!(costs.iter().all(|cost| *cost >= 1.0));
+    }
+
+    #[test]
+    fn test_subsurface_energy() {
+        // a non absorbing medium under a white sky must neither lose nor create light
+        let mut world = World::new();
+        world.add_material("skin", Box::new(Subsurface::new(Vec3::new_diagonal(0.1), Vec3::new_diagonal(1.0), 0.3)));
+        world.add_geometry(Box::new(Sphere::new(Vec3::new(0.0, 0.0, -1.0), 0.5, world.get_material("skin").unwrap())));
+        let lights = world.get_lights();
+        let world = world.get_accelerator(AcceleratorType::default());
+
+        let sampler = CpuSampler::new(1, 64, Vec3::new_diagonal(1.0));
+        let mut stats = TraversalStats::default();
+        let n = 2000;
+        let mut sum = Vec3::zero();
+        for i in 0..n {
+            let t = i as Float / n as Float;
+            let ray = Ray::new(Vec3::new(-0.4 + 0.8 * t, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            let sampled = sampler.single_point_sampling(world.clone(), &lights, SamplePoint { x: 0, y: 0, ray }, &mut stats);
+            assert!(sampled.color.x.is_finite());
+            sum += sampled.color;
+        }
+        let mean = sum / n as Float;
+        assert!(mean.x > 0.9 && mean.x < 1.05, "mean {}", mean);
+    }
+
+    #[test]
+    fn test_direct_lighting() {
+        // a small light 1 above a diffuse floor: E = L * A / d^2, reflected as albedo / pi * E
+        let mut world = World::new();
+        world.add_material("floor", Box::new(Lambertian::new(Vec3::new_diagonal(0.5))));
+        world.add_material("light", Box::new(Light::new(Vec3::new_diagonal(100.0))));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-5.0, 0.0, -5.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 10.0),
+            world.get_material("floor").unwrap(),
+        )));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-0.05, 1.0, -0.05),
+            Vec3::new(0.1, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.1),
+            world.get_material("light").unwrap(),
+        )));
+        let lights = world.get_lights();
+        assert!(!lights.is_empty());
+        let accelerator = world.get_accelerator(AcceleratorType::default());
+
+        // a single bounce sees the light only through light sampling
+        let sampler = CpuSampler::new(1, 1, Vec3::zero());
+        let mut stats = TraversalStats::default();
+        let ray = Ray::new(Vec3::new(1.0, 0.5, 0.0), Vec3::new(-1.0, -0.5, 0.0));
+        let n = 1000;
+        let mut sum = Vec3::zero();
+        for _ in 0..n {
+            sum += sampler.single_point_sampling(accelerator.clone(), &lights, SamplePoint { x: 0, y: 0, ray }, &mut stats).color;
+        }
+        let expected = 0.5 / FloatConsts::PI;
+        assert!((sum.x / n as Float - expected).abs() < 0.02 * expected, "mean {}", sum / n as Float);
+
+        // and nothing once the light is blocked
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-0.5, 0.5, -0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            world.get_material("floor").unwrap(),
+        )));
+        let accelerator = world.get_accelerator(AcceleratorType::default());
+        let sampled = sampler.single_point_sampling(accelerator, &lights, SamplePoint { x: 0, y: 0, ray }, &mut stats);
+        assert_eq!(sampled.color, Vec3::zero());
+    }
+
+    #[test]
+    fn test_mis() {
+        // a large light 1 above a diffuse floor, reached by both light and BSDF samples
+        let mut world = World::new();
+        world.add_material("floor", Box::new(Lambertian::new(Vec3::new_diagonal(0.5))));
+        world.add_material("light", Box::new(Light::new(Vec3::new_diagonal(1.0))));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-5.0, 0.0, -5.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 10.0),
+            world.get_material("floor").unwrap(),
+        )));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-2.0, 1.0, -2.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            world.get_material("light").unwrap(),
+        )));
+        let lights = world.get_lights();
+        let accelerator = world.get_accelerator(AcceleratorType::default());
+
+        // point to rectangle form factor, four 2 x 2 corner rectangles at height 1
+        let a: Float = 2.0;
+        let s = (1.0 + a * a).sqrt();
+        let form_factor = 4.0 * (2.0 * a / s * (a / s).atan()) / (2.0 * FloatConsts::PI);
+        let expected = 0.5 * form_factor;
+
+        // with a single bounce the light sample has to carry all of it
+        let ray = Ray::new(Vec3::new(1.0, 0.5, 0.0), Vec3::new(-1.0, -0.5, 0.0));
+        for (heuristic, max_bounces) in [(MisHeuristic::Balance, 2), (MisHeuristic::Power, 2), (MisHeuristic::Power, 1)] {
+            let sampler = CpuSampler::new(1, max_bounces, Vec3::zero()).with_mis_heuristic(heuristic);
+            let mut stats = TraversalStats::default();
+            let n = 16000;
+            let mut sum = Vec3::zero();
+            for _ in 0..n {
+                sum += sampler.single_point_sampling(accelerator.clone(), &lights, SamplePoint { x: 0, y: 0, ray }, &mut stats).color;
+            }
+            let mean = sum / n as Float;
+            assert!((mean.x - expected).abs() < 0.03 * expected, "{:?}, {} bounces: mean {}, expected {}", heuristic, max_bounces, mean, expected);
+        }
+    }
+
+    #[test]
+    fn test_mis_stacked_lights() {
+        // the light of test_mis with a second one right above it, which the floor never sees
+        let mut world = World::new();
+        world.add_material("floor", Box::new(Lambertian::new(Vec3::new_diagonal(0.5))));
+        world.add_material("light", Box::new(Light::new(Vec3::new_diagonal(1.0))));
+        world.add_geometry(Box::new(Quad::new(
+            Vec3::new(-5.0, 0.0, -5.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 10.0),
+            world.get_material("floor").unwrap(),
+        )));
+        for height in [1.0, 1.5] {
+            world.add_geometry(Box::new(Quad::new(
+                Vec3::new(-2.0, height, -2.0),
+                Vec3::new(4.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 4.0),
+                world.get_material("light").unwrap(),
+            )));
+        }
+        let lights = world.get_lights();
+        let accelerator = world.get_accelerator(AcceleratorType::default());
+
+        let a: Float = 2.0;
+        let s = (1.0 + a * a).sqrt();
+        let form_factor = 4.0 * (2.0 * a / s * (a / s).atan()) / (2.0 * FloatConsts::PI);
+        let expected = 0.5 * form_factor;
+
+        let ray = Ray::new(Vec3::new(1.0, 0.5, 0.0), Vec3::new(-1.0, -0.5, 0.0));
+        let sampler = CpuSampler::new(1, 2, Vec3::zero()).with_mis_heuristic(MisHeuristic::Balance);
+        let mut stats = TraversalStats::default();
+        let n = 4000;
+        let mut sum = Vec3::zero();
+        for _ in 0..n {
+            sum += sampler.single_point_sampling(accelerator.clone(), &lights, SamplePoint { x: 0, y: 0, ray }, &mut stats).color;
+        }
+        let mean = sum / n as Float;
+        assert!((mean.x - expected).abs() < 0.03 * expected, "mean {}, expected {}", mean, expected);
+    }
+
+    // Diffuse surface that also emits, so paths neither end at a light nor escape
+    struct Glowing(Lambertian);
+
+    impl Material for Glowing {
+        fn lobes(&self) -> Lobe {
+            self.0.lobes()
+        }
+
+        fn eval(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Vec3 {
+            self.0.eval(rec, wo, wi)
+        }
+
+        fn sample(&self, rec: &HitRecord, wo: &Vec3) -> Option<BsdfSample> {
+            self.0.sample(rec, wo)
+        }
+
+        fn pdf(&self, rec: &HitRecord, wo: &Vec3, wi: &Vec3) -> Float {
+            self.0.pdf(rec, wo, wi)
+        }
+
+        fn emitted(&self, _rec: &HitRecord, _wo: &Vec3) -> Option<Vec3> {
+            Some(Vec3::new_diagonal(0.5))
+        }
+
+        fn is_emissive(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_russian_roulette() {
+        // inside a closed sphere emitting 0.5 and reflecting half: L = 0.5 / (1 - 0.5) = 1
+        let mut world = World::new();
+        world.add_material("glow", Box::new(Glowing(Lambertian::new(Vec3::new_diagonal(0.5)))));
+        world.add_geometry(Box::new(Sphere::new(Vec3::zero(), 1.0, world.get_material("glow").unwrap())));
+        let lights = world.get_lights();
+        let accelerator = world.get_accelerator(AcceleratorType::default());
+
+        let render = |sampler: CpuSampler| {
+            let mut stats = TraversalStats::default();
+            let n = 4000;
+            let mut sum = Vec3::zero();
+            for _ in 0..n {
+                let ray = Ray::new(Vec3::zero(), Vec3::new_random_unit_vector());
+                sum += sampler.single_point_sampling(accelerator.clone(), &lights, SamplePoint { x: 0, y: 0, ray }, &mut stats).color;
+            }
+            (sum / n as Float, stats.rays)
+        };
+        let (full, full_rays) = render(CpuSampler::new(1, 64, Vec3::zero()));
+        let (roulette, roulette_rays) = render(CpuSampler::new(1, 64, Vec3::zero()).with_russian_roulette(1));
+        assert!((full.x - 1.0).abs() < 0.02, "mean {}", full);
+        assert!((roulette.x - 1.0).abs() < 0.05, "mean {}", roulette);
+        assert!(roulette_rays * 4 < full_rays, "{} rays with roulette, {} without", roulette_rays, full_rays);
+    }
+
+    #[test]
+    fn test_environment_light() {
+        // a convex diffuse object under a uniform environment reflects albedo times its radiance,
+        // whatever mix of light and BSDF samples finds it
+        let mut world = World::new();
+        world.add_material("diffuse", Box::new(Lambertian::new(Vec3::new_diagonal(0.5))));
+        world.add_geometry(Box::new(Sphere::new(Vec3::zero(), 1.0, world.get_material("diffuse").unwrap())));
+        let environment = ImageTexture::new(4, 2, vec![Vec3::new_diagonal(1.0); 8]);
+        world.add_light(Box::new(EnvironmentLight::new(environment).with_intensity(2.0)));
+        let lights = world.get_lights();
+        let accelerator = world.get_accelerator(AcceleratorType::default());
+
+        // the background color is only used when there is no environment
+        let sampler = CpuSampler::new(1, 4, Vec3::new(5.0, 0.0, 0.0));
+        let mut stats = TraversalStats::default();
+        let n = 4000;
+        let mut sum = Vec3::zero();
+        for i in 0..n {
+            let t = i as Float / n as Float;
+            let ray = Ray::new(Vec3::new(-0.9 + 1.8 * t, 0.3, 3.0), Vec3::new(0.0, 0.0, -1.0));
+            sum += sampler.single_point_sampling(accelerator.clone(), &lights, SamplePoint { x: 0, y: 0, ray }, &mut stats).color;
+        }
+        let mean = sum / n as Float;
+        assert!((mean - Vec3::new_diagonal(1.0)).length() < 0.05, "mean {}", mean);
+    }
+}
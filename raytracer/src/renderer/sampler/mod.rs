@@ -4,6 +4,8 @@ use flume::{Receiver, Sender};
 
 mod cpu;
 pub use cpu::CpuSampler;
+mod bdpt;
+pub use bdpt::BdptSampler;
 
 pub mod metal;
 
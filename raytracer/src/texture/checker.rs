@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{material::library::{TextureDesc, TextureKind}, math::vec3::Vec3, Float};
+
+use super::{solid::SolidColor, Texture};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckerMode {
+    // alternates over world space cubes of size `scale`
+    Solid,
+    // alternates over `scale` x `scale` squares in uv space
+    Uv,
+}
+
+#[derive(Clone)]
+pub struct CheckerTexture {
+    even: Arc<Box<dyn Texture>>,
+    odd: Arc<Box<dyn Texture>>,
+    scale: Float,
+    mode: CheckerMode,
+}
+
+impl CheckerTexture {
+    pub fn new(
+        mode: CheckerMode,
+        scale: Float,
+        even: Arc<Box<dyn Texture>>,
+        odd: Arc<Box<dyn Texture>>,
+    ) -> Self {
+        Self { even, odd, scale, mode }
+    }
+
+    pub fn new_with_colors(mode: CheckerMode, scale: Float, even: Vec3, odd: Vec3) -> Self {
+        Self::new(
+            mode,
+            scale,
+            Arc::new(Box::new(SolidColor::new(even))),
+            Arc::new(Box::new(SolidColor::new(odd))),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: Float, v: Float, point: &Vec3) -> Vec3 {
+        let parity = match self.mode {
+            CheckerMode::Solid => {
+                let inv_scale = Float::from(1.0) / self.scale;
+                (0..3).map(|i| (point[i] * inv_scale).floor() as i64).sum::<i64>()
+            }
+            CheckerMode::Uv => {
+                (u * self.scale).floor() as i64 + (v * self.scale).floor() as i64
+            }
+        };
+        if parity.rem_euclid(2) == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Texture(TextureKind::Checker {
+            mode: self.mode,
+            scale: self.scale,
+            even: Box::new(self.even.describe()?),
+            odd: Box::new(self.odd.describe()?),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parity() {
+        let black = Vec3::zero();
+        let white = Vec3::new_diagonal(1.0);
+
+        let solid = CheckerTexture::new_with_colors(CheckerMode::Solid, 0.5, white, black);
+        assert_eq!(solid.value(0.0, 0.0, &Vec3::new(0.1, 0.1, 0.1)), white);
+        assert_eq!(solid.value(0.0, 0.0, &Vec3::new(0.6, 0.1, 0.1)), black);
+        assert_eq!(solid.value(0.0, 0.0, &Vec3::new(0.6, 0.6, 0.1)), white);
+        // cells keep alternating across zero
+        assert_eq!(solid.value(0.0, 0.0, &Vec3::new(-0.1, 0.1, 0.1)), black);
+        assert_eq!(solid.value(0.0, 0.0, &Vec3::new(-0.1, -0.1, 0.1)), white);
+
+        let uv = CheckerTexture::new_with_colors(CheckerMode::Uv, 4.0, white, black);
+        assert_eq!(uv.value(0.1, 0.1, &Vec3::zero()), white);
+        assert_eq!(uv.value(0.3, 0.1, &Vec3::zero()), black);
+        assert_eq!(uv.value(0.3, 0.3, &Vec3::zero()), white);
+        // the point doesn't matter in uv mode
+        assert_eq!(uv.value(0.3, 0.1, &Vec3::new(5.3, -2.0, 0.7)), black);
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+
+use image::ImageResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{material::library::{TextureDesc, TextureKind}, math::vec3::Vec3, Float};
+
+use super::Texture;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterMode {
+    Nearest,
+    Bilinear,
+}
+
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec3>,
+    wrap_mode: WrapMode,
+    filter_mode: FilterMode,
+    // file the texels came from and whether they were read as linear data
+    source: Option<(PathBuf, bool)>,
+}
+
+impl ImageTexture {
+    // Color images (PNG, JPEG, ...) are stored gamma encoded,
+    // so they are decoded with the same 2.2 gamma the imager encodes with.
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        Self::open_with_gamma(path, 2.2)
+    }
+
+    // For data textures such as normal or roughness maps
+    pub fn open_linear<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        Self::open_with_gamma(path, 1.0)
+    }
+
+    fn open_with_gamma<P: AsRef<Path>>(path: P, gamma: Float) -> ImageResult<Self> {
+        let source = Some((path.as_ref().to_path_buf(), gamma == 1.0));
+        let image = image::open(path)?.to_rgb32f();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let texels = image.pixels().map(|p| {
+            Vec3::new(p[0].powf(gamma), p[1].powf(gamma), p[2].powf(gamma))
+        }).collect();
+        let mut texture = Self::new(width, height, texels);
+        texture.source = source;
+        Ok(texture)
+    }
+
+    pub fn new(width: usize, height: usize, texels: Vec<Vec3>) -> Self {
+        assert_eq!(width * height, texels.len(), "texel count doesn't match the texture size");
+        Self {
+            width,
+            height,
+            texels,
+            wrap_mode: WrapMode::Repeat,
+            filter_mode: FilterMode::Bilinear,
+            source: None,
+        }
+    }
+
+    pub fn with_wrap_mode(mut self, wrap_mode: WrapMode) -> Self {
+        self.wrap_mode = wrap_mode;
+        self
+    }
+
+    pub fn with_filter_mode(mut self, filter_mode: FilterMode) -> Self {
+        self.filter_mode = filter_mode;
+        self
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn texel(&self, x: i64, y: i64) -> Vec3 {
+        let x = Self::wrap(x, self.width, self.wrap_mode);
+        let y = Self::wrap(y, self.height, self.wrap_mode);
+        self.texels[y * self.width + x]
+    }
+
+    fn wrap(i: i64, size: usize, wrap_mode: WrapMode) -> usize {
+        let size = size as i64;
+        let wrapped = match wrap_mode {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * size);
+                if i < size { i } else { 2 * size - 1 - i }
+            }
+            WrapMode::Clamp => i.clamp(0, size - 1),
+        };
+        wrapped as usize
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: Float, v: Float, _point: &Vec3) -> Vec3 {
+        if self.texels.is_empty() {
+            return Vec3::new(0.0, 1.0, 1.0);
+        }
+        // v grows upward while image rows grow downward
+        let x = u * self.width as Float;
+        let y = (Float::from(1.0) - v) * self.height as Float;
+        match self.filter_mode {
+            FilterMode::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            FilterMode::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = (1.0 - fx) * self.texel(x0, y0) + fx * self.texel(x0 + 1, y0);
+                let bottom = (1.0 - fx) * self.texel(x0, y0 + 1) + fx * self.texel(x0 + 1, y0 + 1);
+                (1.0 - fy) * top + fy * bottom
+            }
+        }
+    }
+
+    // In-memory images have no file to point at
+    fn describe(&self) -> Option<TextureDesc> {
+        let (path, linear) = self.source.clone()?;
+        Some(TextureDesc::Texture(TextureKind::Image {
+            path,
+            linear,
+            wrap: self.wrap_mode,
+            filter: self.filter_mode,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dummy_texture() -> ImageTexture {
+        ImageTexture::new(2, 2, vec![
+            Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 1.0, 1.0),
+        ])
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        let texture = dummy_texture().with_filter_mode(FilterMode::Nearest);
+        let origin = Vec3::zero();
+        assert_eq!(texture.value(0.25, 0.75, &origin), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.value(1.25, 0.75, &origin), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.value(0.75, 0.25, &origin), Vec3::new(1.0, 1.0, 1.0));
+
+        let texture = texture.with_wrap_mode(WrapMode::Clamp);
+        assert_eq!(texture.value(1.75, 0.25, &origin), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(texture.value(-3.0, 0.75, &origin), Vec3::new(1.0, 0.0, 0.0));
+
+        let texture = texture.with_wrap_mode(WrapMode::Mirror);
+        assert_eq!(texture.value(1.25, 0.75, &origin), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_bilinear_filtering() {
+        let texture = dummy_texture().with_wrap_mode(WrapMode::Clamp);
+        let origin = Vec3::zero();
+        assert_eq!(texture.value(0.25, 0.75, &origin), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.value(0.5, 0.75, &origin), Vec3::new(0.5, 0.5, 0.0));
+        assert_eq!(texture.value(0.5, 0.5, &origin), Vec3::new(0.5, 0.5, 0.5));
+    }
+}
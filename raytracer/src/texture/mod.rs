@@ -0,0 +1,18 @@
+use as_any::AsAny;
+
+use crate::{material::library::TextureDesc, math::vec3::Vec3, Float};
+
+pub trait Texture: AsAny {
+    fn value(&self, u: Float, v: Float, point: &Vec3) -> Vec3;
+
+    // Data file form of this texture, None when it can't be written out
+    fn describe(&self) -> Option<TextureDesc> {
+        None
+    }
+}
+
+pub mod solid;
+pub mod checker;
+pub mod image;
+pub mod noise;
+pub mod procedural;
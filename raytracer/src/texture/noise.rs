@@ -0,0 +1,192 @@
+use crate::{math::vec3::Vec3, Float};
+
+const POINT_COUNT: usize = 256;
+
+// SplitMix64: tiny, seedable and stable, so a seed always gives the same pattern
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn unit_float(state: &mut u64) -> Float {
+    (splitmix64(state) >> 40) as Float / (1u64 << 24) as Float
+}
+
+fn hash_cell(seed: u64, x: i64, y: i64, z: i64) -> u64 {
+    let mut state = seed
+        ^ (x as u64).wrapping_mul(0x8da6b343)
+        ^ (y as u64).wrapping_mul(0xd8163841)
+        ^ (z as u64).wrapping_mul(0xcb1ab31f);
+    splitmix64(&mut state)
+}
+
+// Gradient noise, returns values roughly in [-1, 1]
+#[derive(Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut state = seed;
+        let gradients = (0..POINT_COUNT).map(|_| loop {
+            let v = Vec3::new(
+                2.0 * unit_float(&mut state) - 1.0,
+                2.0 * unit_float(&mut state) - 1.0,
+                2.0 * unit_float(&mut state) - 1.0,
+            );
+            let len = v.squared_length();
+            if len > 1e-4 && len <= 1.0 {
+                break v.normalized();
+            }
+        }).collect();
+        let perm_x = Self::permutation(&mut state);
+        let perm_y = Self::permutation(&mut state);
+        let perm_z = Self::permutation(&mut state);
+        Self { gradients, perm_x, perm_y, perm_z }
+    }
+
+    fn permutation(state: &mut u64) -> Vec<usize> {
+        let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+        for i in (1..POINT_COUNT).rev() {
+            let target = (splitmix64(state) % (i as u64 + 1)) as usize;
+            perm.swap(i, target);
+        }
+        perm
+    }
+
+    pub fn noise(&self, p: &Vec3) -> Float {
+        let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (u, v, w) = (p.x - fx, p.y - fy, p.z - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+
+        let mut accum = Float::from(0.0);
+        // Hermite smoothing hides the grid
+        let (uu, vv, ww) = (u*u*(3.0 - 2.0*u), v*v*(3.0 - 2.0*v), w*w*(3.0 - 2.0*w));
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let idx = self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize];
+                    let weight = Vec3::new(u - di as Float, v - dj as Float, w - dk as Float);
+                    let (di, dj, dk) = (di as Float, dj as Float, dk as Float);
+                    accum += (di*uu + (1.0 - di)*(1.0 - uu))
+                        * (dj*vv + (1.0 - dj)*(1.0 - vv))
+                        * (dk*ww + (1.0 - dk)*(1.0 - ww))
+                        * self.gradients[idx].dot(&weight);
+                }
+            }
+        }
+        accum
+    }
+
+    // Fractional Brownian motion: octaves of signed noise, each at double frequency and half weight
+    pub fn fbm(&self, p: &Vec3, octaves: usize) -> Float {
+        let mut accum = Float::from(0.0);
+        let mut p = *p;
+        let mut weight = Float::from(1.0);
+        for _ in 0..octaves {
+            accum += weight * self.noise(&p);
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        accum
+    }
+
+    // Like fbm, but sums the absolute value of each octave
+    pub fn turbulence(&self, p: &Vec3, octaves: usize) -> Float {
+        let mut accum = Float::from(0.0);
+        let mut p = *p;
+        let mut weight = Float::from(1.0);
+        for _ in 0..octaves {
+            accum += weight * self.noise(&p).abs();
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        accum
+    }
+}
+
+// Cellular noise with one feature point per unit cell
+#[derive(Clone)]
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    fn feature_point(&self, x: i64, y: i64, z: i64) -> Vec3 {
+        let mut state = hash_cell(self.seed, x, y, z);
+        Vec3::new(
+            x as Float + unit_float(&mut state),
+            y as Float + unit_float(&mut state),
+            z as Float + unit_float(&mut state),
+        )
+    }
+
+    // Distances to the closest and second closest feature points (F1, F2)
+    pub fn noise(&self, p: &Vec3) -> (Float, Float) {
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        let mut f1 = Float::INFINITY;
+        let mut f2 = Float::INFINITY;
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let distance = (self.feature_point(i + di, j + dj, k + dk) - *p).length();
+                    if distance < f1 {
+                        f2 = f1;
+                        f1 = distance;
+                    } else if distance < f2 {
+                        f2 = distance;
+                    }
+                }
+            }
+        }
+        (f1, f2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perlin_deterministic() {
+        let a = Perlin::new(42);
+        let b = Perlin::new(42);
+        let c = Perlin::new(7);
+        let mut differs = false;
+        for i in 0..100 {
+            let p = Vec3::new(i as Float * 0.37, i as Float * 0.11 - 3.0, i as Float * -0.23);
+            let value = a.noise(&p);
+            assert_eq!(value, b.noise(&p));
+            assert!(value.abs() <= 1.5);
+            differs |= value != c.noise(&p);
+        }
+        assert!(differs);
+        assert!(a.noise(&Vec3::new(1.0, 2.0, 3.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_worley_deterministic() {
+        let a = Worley::new(3);
+        let b = Worley::new(3);
+        for i in 0..100 {
+            let p = Vec3::new(i as Float * 0.53, -(i as Float) * 0.29, i as Float * 0.07);
+            let (f1, f2) = a.noise(&p);
+            assert_eq!((f1, f2), b.noise(&p));
+            assert!(0.0 <= f1 && f1 <= f2);
+            assert!(f1 < 3.0_f32.sqrt());
+        }
+    }
+}
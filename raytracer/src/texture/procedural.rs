@@ -0,0 +1,212 @@
+use crate::{material::library::{to_array, TextureDesc, TextureKind}, math::vec3::Vec3, Float};
+
+use super::{noise::{Perlin, Worley}, Texture};
+
+const TURBULENCE_OCTAVES: usize = 7;
+
+fn mix(a: Vec3, b: Vec3, t: Float) -> Vec3 {
+    (1.0 - t) * a + t * b
+}
+
+// Grey fBm noise scaled by `scale`, mapped to [0, 1]
+#[derive(Clone)]
+pub struct NoiseTexture {
+    seed: u64,
+    perlin: Perlin,
+    scale: Float,
+    octaves: usize,
+}
+
+impl NoiseTexture {
+    pub fn new(seed: u64, scale: Float, octaves: usize) -> Self {
+        Self { seed, perlin: Perlin::new(seed), scale, octaves }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: Float, _v: Float, point: &Vec3) -> Vec3 {
+        let n = self.perlin.fbm(&(self.scale * *point), self.octaves);
+        Vec3::new_diagonal((0.5 * (1.0 + n)).clamp(0.0, 1.0))
+    }
+
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Texture(TextureKind::Noise { seed: self.seed, scale: self.scale, octaves: self.octaves }))
+    }
+}
+
+// Veins along the z axis, distorted by turbulence
+#[derive(Clone)]
+pub struct MarbleTexture {
+    seed: u64,
+    perlin: Perlin,
+    scale: Float,
+    distortion: Float,
+    base: Vec3,
+    vein: Vec3,
+}
+
+impl MarbleTexture {
+    pub fn new(seed: u64, scale: Float, base: Vec3, vein: Vec3) -> Self {
+        Self { seed, perlin: Perlin::new(seed), scale, distortion: 10.0, base, vein }
+    }
+
+    pub fn with_distortion(mut self, distortion: Float) -> Self {
+        self.distortion = distortion;
+        self
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: Float, _v: Float, point: &Vec3) -> Vec3 {
+        let p = self.scale * *point;
+        let phase = p.z + self.distortion * self.perlin.turbulence(&p, TURBULENCE_OCTAVES);
+        let t = 0.5 * (1.0 + phase.sin());
+        mix(self.vein, self.base, t)
+    }
+
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Texture(TextureKind::Marble {
+            seed: self.seed,
+            scale: self.scale,
+            base: to_array(self.base),
+            vein: to_array(self.vein),
+            distortion: self.distortion,
+        }))
+    }
+}
+
+// Concentric growth rings around the y axis
+#[derive(Clone)]
+pub struct WoodTexture {
+    seed: u64,
+    perlin: Perlin,
+    scale: Float,
+    rings: Float,
+    light: Vec3,
+    dark: Vec3,
+}
+
+impl WoodTexture {
+    pub fn new(seed: u64, scale: Float, rings: Float, light: Vec3, dark: Vec3) -> Self {
+        Self { seed, perlin: Perlin::new(seed), scale, rings, light, dark }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: Float, _v: Float, point: &Vec3) -> Vec3 {
+        let p = self.scale * *point;
+        let radius = (p.x * p.x + p.z * p.z).sqrt();
+        let grain = self.rings * radius + 2.0 * self.perlin.fbm(&Vec3::new(p.x, 0.1 * p.y, p.z), 4);
+        let ring = grain - grain.floor();
+        // sharp latewood edge, slow earlywood falloff
+        let t = ring.powf(3.0);
+        mix(self.light, self.dark, t)
+    }
+
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Texture(TextureKind::Wood {
+            seed: self.seed,
+            scale: self.scale,
+            rings: self.rings,
+            light: to_array(self.light),
+            dark: to_array(self.dark),
+        }))
+    }
+}
+
+// Speckled stone from cellular noise, mottled with fBm
+#[derive(Clone)]
+pub struct GraniteTexture {
+    seed: u64,
+    worley: Worley,
+    perlin: Perlin,
+    scale: Float,
+    base: Vec3,
+    speckle: Vec3,
+}
+
+impl GraniteTexture {
+    pub fn new(seed: u64, scale: Float, base: Vec3, speckle: Vec3) -> Self {
+        Self { seed, worley: Worley::new(seed), perlin: Perlin::new(seed ^ 0x5eed), scale, base, speckle }
+    }
+}
+
+impl Texture for GraniteTexture {
+    fn value(&self, _u: Float, _v: Float, point: &Vec3) -> Vec3 {
+        let p = self.scale * *point;
+        let (f1, f2) = self.worley.noise(&p);
+        let crystal = ((f2 - f1) * 2.5).clamp(0.0, 1.0);
+        let mottle = 0.85 + 0.15 * self.perlin.fbm(&(4.0 * p), 3);
+        mottle * mix(self.speckle, self.base, crystal)
+    }
+
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Texture(TextureKind::Granite {
+            seed: self.seed,
+            scale: self.scale,
+            base: to_array(self.base),
+            speckle: to_array(self.speckle),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::random::random;
+
+    use super::*;
+
+    fn points() -> Vec<Vec3> {
+        (0..500).map(|_| Vec3::new_random_range(-10.0..10.0)).collect()
+    }
+
+    // Each channel of color lies between the channels of a and b, give or take a relative margin
+    fn assert_between(color: Vec3, a: Vec3, b: Vec3, margin: Float) {
+        for i in 0..3 {
+            let (low, high) = (a[i].min(b[i]), a[i].max(b[i]));
+            assert!(color[i] >= low * (1.0 - margin) - 1e-5 && color[i] <= high * (1.0 + margin) + 1e-5, "{:?} outside {:?}..{:?}", color, a, b);
+        }
+    }
+
+    fn assert_deterministic(a: &dyn Texture, b: &dyn Texture, other_seed: &dyn Texture) {
+        let points = points();
+        let mut differs = false;
+        for p in &points {
+            let (u, v) = (random::<Float>(), random::<Float>());
+            assert_eq!(a.value(u, v, p), b.value(u, v, p));
+            differs |= a.value(u, v, p) != other_seed.value(u, v, p);
+        }
+        assert!(differs, "another seed gives the same pattern");
+    }
+
+    #[test]
+    fn test_marble() {
+        let (base, vein) = (Vec3::new(0.9, 0.9, 0.85), Vec3::new(0.2, 0.25, 0.3));
+        let marble = MarbleTexture::new(7, 2.0, base, vein);
+        assert_deterministic(&marble, &MarbleTexture::new(7, 2.0, base, vein), &MarbleTexture::new(8, 2.0, base, vein));
+        for p in points() {
+            assert_between(marble.value(0.0, 0.0, &p), base, vein, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_wood() {
+        let (light, dark) = (Vec3::new(0.8, 0.6, 0.4), Vec3::new(0.4, 0.25, 0.1));
+        let wood = WoodTexture::new(3, 1.0, 8.0, light, dark);
+        assert_deterministic(&wood, &WoodTexture::new(3, 1.0, 8.0, light, dark), &WoodTexture::new(4, 1.0, 8.0, light, dark));
+        for p in points() {
+            assert_between(wood.value(0.0, 0.0, &p), light, dark, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_granite() {
+        let (base, speckle) = (Vec3::new(0.6, 0.55, 0.5), Vec3::new(0.1, 0.1, 0.1));
+        let granite = GraniteTexture::new(11, 3.0, base, speckle);
+        assert_deterministic(&granite, &GraniteTexture::new(11, 3.0, base, speckle), &GraniteTexture::new(12, 3.0, base, speckle));
+        // the mottle darkens or brightens by at most the three fBm octaves
+        for p in points() {
+            assert_between(granite.value(0.0, 0.0, &p), base, speckle, 0.15 * 1.75);
+        }
+    }
+}
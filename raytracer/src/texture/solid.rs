@@ -0,0 +1,24 @@
+use crate::{material::library::{to_array, TextureDesc}, math::vec3::Vec3, Float};
+
+use super::Texture;
+
+#[derive(Clone)]
+pub struct SolidColor {
+    color: Vec3,
+}
+
+impl SolidColor {
+    pub fn new(color: Vec3) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: Float, _v: Float, _point: &Vec3) -> Vec3 {
+        self.color
+    }
+
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Color(to_array(self.color)))
+    }
+}
@@ -0,0 +1,116 @@
+use crate::Float;
+
+// Piecewise constant density over [0, 1), sampled by inverting its CDF
+#[derive(Clone)]
+pub struct Distribution1D {
+    func: Vec<Float>,
+    cdf: Vec<Float>,
+    integral: Float,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<Float>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i].max(0.0) / n as Float;
+        }
+        let integral = cdf[n];
+        if integral > 0.0 {
+            cdf.iter_mut().for_each(|c| *c /= integral);
+        } else {
+            // nothing to prefer, fall back to uniform
+            cdf.iter_mut().enumerate().for_each(|(i, c)| *c = i as Float / n as Float);
+        }
+        Self { func, cdf, integral }
+    }
+
+    pub fn integral(&self) -> Float {
+        self.integral
+    }
+
+    // Maps a uniform number in [0, 1) to a position in [0, 1),
+    // returning it with its density and the segment it falls in
+    pub fn sample(&self, u: Float) -> (Float, Float, usize) {
+        let n = self.func.len();
+        let index = self.cdf.partition_point(|c| *c <= u).clamp(1, n) - 1;
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+        let x = ((index as Float + offset) / n as Float).min(1.0 - Float::EPSILON);
+        (x, self.pdf(x), index)
+    }
+
+    // Picks a segment, returning it with its probability
+    pub fn sample_discrete(&self, u: Float) -> (usize, Float) {
+        let (_, _, index) = self.sample(u);
+        (index, self.discrete_pdf(index))
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> Float {
+        self.pdf((index as Float + 0.5) / self.func.len() as Float) / self.func.len() as Float
+    }
+
+    pub fn pdf(&self, x: Float) -> Float {
+        let n = self.func.len();
+        let index = ((x * n as Float) as usize).min(n - 1);
+        if self.integral > 0.0 {
+            self.func[index].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+// Piecewise constant density over [0, 1)^2 given as rows of values: a row
+// is picked from the marginal distribution, then a column within the row
+#[derive(Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(width: usize, height: usize, func: &[Float]) -> Self {
+        assert_eq!(width * height, func.len(), "value count doesn't match the distribution size");
+        let rows: Vec<Distribution1D> = func.chunks(width).map(|row| Distribution1D::new(row.to_vec())).collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+        Self { rows, marginal }
+    }
+
+    // Returns (x, y) with x along rows and y across them, and its density
+    pub fn sample(&self, u: Float, v: Float) -> ((Float, Float), Float) {
+        let (y, pdf_y, row) = self.marginal.sample(v);
+        let (x, pdf_x, _) = self.rows[row].sample(u);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, x: Float, y: Float) -> Float {
+        let row = ((y * self.rows.len() as Float) as usize).min(self.rows.len() - 1);
+        self.marginal.pdf(y) * self.rows[row].pdf(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::random::random;
+
+    use super::*;
+
+    #[test]
+    fn test_sample_density() {
+        let func = [0.0, 1.0, 3.0, 0.0, 2.0, 2.0];
+        let distribution = Distribution2D::new(3, 2, &func);
+        let n = 60000;
+        let mut counts = [0usize; 6];
+        for _ in 0..n {
+            let ((x, y), pdf) = distribution.sample(random(), random());
+            assert!((pdf - distribution.pdf(x, y)).abs() < 1e-4);
+            counts[(y * 2.0) as usize * 3 + (x * 3.0) as usize] += 1;
+        }
+        let total: Float = func.iter().sum();
+        for (count, value) in counts.iter().zip(func) {
+            let expected = value / total;
+            assert!((*count as Float / n as Float - expected).abs() < 0.01, "{:?}", counts);
+        }
+    }
+}
@@ -89,6 +89,27 @@ impl Color {
         Color { r: value, g: value, b: value }
     }
 
+    // False-color ramp for t in [0, 1]: blue -> cyan -> green -> yellow -> red
+    pub fn new_heatmap(t: Float) -> Color {
+        const STOPS: [(Float, Float, Float); 5] = [
+            (0.0, 0.0, 1.0),
+            (0.0, 1.0, 1.0),
+            (0.0, 1.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (1.0, 0.0, 0.0),
+        ];
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let scaled = t * (STOPS.len() - 1) as Float;
+        let i = (scaled as usize).min(STOPS.len() - 2);
+        let frac = scaled - i as Float;
+        let (a, b) = (STOPS[i], STOPS[i + 1]);
+        Color {
+            r: a.0 + (b.0 - a.0) * frac,
+            g: a.1 + (b.1 - a.1) * frac,
+            b: a.2 + (b.2 - a.2) * frac,
+        }
+    }
+
     pub fn gamma_correction(&self, gamma: Float) -> Color {
         Color {
             r: self.r.powf(1.0 / gamma),
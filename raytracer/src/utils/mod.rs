@@ -1,2 +1,4 @@
 pub mod image;
-pub mod random;
\ No newline at end of file
+pub mod distribution;
+pub mod random;
+pub mod spectrum;
\ No newline at end of file
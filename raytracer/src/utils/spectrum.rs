@@ -0,0 +1,99 @@
+use std::sync::OnceLock;
+
+use crate::{math::vec3::Vec3, utils::random::random, Float};
+
+// Visible range in nanometers
+pub const WAVELENGTH_MIN: Float = 380.0;
+pub const WAVELENGTH_MAX: Float = 780.0;
+
+// Wavelengths used when a spectral quantity is integrated down to RGB
+const INTEGRATION_STEPS: usize = 32;
+
+fn gaussian(x: Float, mu: Float, sigma_low: Float, sigma_high: Float) -> Float {
+    let sigma = if x < mu { sigma_low } else { sigma_high };
+    let t = (x - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+// CIE 1931 color matching functions, multi-lobe fit of Wyman et al. 2013
+fn xyz(wavelength: Float) -> Vec3 {
+    let x = 1.056 * gaussian(wavelength, 599.8, 37.9, 31.0)
+        + 0.362 * gaussian(wavelength, 442.0, 16.0, 26.7)
+        - 0.065 * gaussian(wavelength, 501.1, 20.4, 26.2);
+    let y = 0.821 * gaussian(wavelength, 568.8, 46.9, 40.5)
+        + 0.286 * gaussian(wavelength, 530.9, 16.3, 31.1);
+    let z = 1.217 * gaussian(wavelength, 437.0, 11.8, 36.0)
+        + 0.681 * gaussian(wavelength, 459.0, 26.0, 13.8);
+    Vec3::new(x, y, z)
+}
+
+// Linear sRGB of a single wavelength, clamped to the gamut
+fn unnormalized_rgb(wavelength: Float) -> Vec3 {
+    let c = xyz(wavelength);
+    Vec3::new(
+        (3.2406 * c.x - 1.5372 * c.y - 0.4986 * c.z).max(0.0),
+        (-0.9689 * c.x + 1.8758 * c.y + 0.0415 * c.z).max(0.0),
+        (0.0557 * c.x - 0.2040 * c.y + 1.0570 * c.z).max(0.0),
+    )
+}
+
+fn normalization() -> Vec3 {
+    static NORMALIZATION: OnceLock<Vec3> = OnceLock::new();
+    *NORMALIZATION.get_or_init(|| {
+        let steps = 4000;
+        let mut sum = Vec3::zero();
+        for i in 0..steps {
+            let t = (i as Float + 0.5) / steps as Float;
+            sum += unnormalized_rgb(WAVELENGTH_MIN + t * (WAVELENGTH_MAX - WAVELENGTH_MIN));
+        }
+        sum / steps as Float
+    })
+}
+
+// RGB weight of a wavelength picked uniformly over the visible range.
+// Averaged over all wavelengths it is exactly white, so a path carrying a
+// single wavelength converges to the right color.
+pub fn wavelength_to_rgb(wavelength: Float) -> Vec3 {
+    unnormalized_rgb(wavelength) / normalization()
+}
+
+pub fn sample_wavelength() -> Float {
+    WAVELENGTH_MIN + random::<Float>() * (WAVELENGTH_MAX - WAVELENGTH_MIN)
+}
+
+// Projects a spectral function to RGB; a constant function keeps its value
+pub fn integrate_rgb(f: impl Fn(Float) -> Float) -> Vec3 {
+    let mut sum = Vec3::zero();
+    let mut weights = Vec3::zero();
+    for i in 0..INTEGRATION_STEPS {
+        let t = (i as Float + 0.5) / INTEGRATION_STEPS as Float;
+        let wavelength = WAVELENGTH_MIN + t * (WAVELENGTH_MAX - WAVELENGTH_MIN);
+        let rgb = unnormalized_rgb(wavelength);
+        sum += f(wavelength) * rgb;
+        weights += rgb;
+    }
+    sum / weights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_white_balance() {
+        let n = 100000;
+        let mut sum = Vec3::zero();
+        for _ in 0..n {
+            sum += wavelength_to_rgb(sample_wavelength());
+        }
+        let mean = sum / n as Float;
+        assert!((mean - Vec3::new_diagonal(1.0)).length() < 0.02, "mean {}", mean);
+
+        let constant = integrate_rgb(|_| 0.5);
+        assert!((constant - Vec3::new_diagonal(0.5)).length() < 1e-5);
+        let red = wavelength_to_rgb(650.0);
+        assert!(red.x > red.y && red.x > red.z);
+        let blue = wavelength_to_rgb(450.0);
+        assert!(blue.z > blue.x && blue.z > blue.y);
+    }
+}